@@ -0,0 +1,78 @@
+use learnrustgrad::loss;
+use learnrustgrad::prelude::*;
+
+fn main() {
+    let a: Scalar = Scalar::new(2.0);
+    let b = Scalar::new(1.0);
+    println!("a {}; b {}", a, b);
+
+    let c = &a + &b;
+    println!("c = &a + &b; c = {}", c);
+    println!("&a + &b = {}", &a + &b);
+    println!("{} + 5.0 = {}", a, &a + 5.0);
+    println!("5.0 + {} = {}", a, 5.0 + &a);
+    println!("&(&a + &b) + 5.0 = {}", &(&a + &b) + 5.0);
+
+    let d = &c + 2.0;
+    let e = Scalar::new(10.0);
+    let f = &d * &e;
+    let g = 0.01 * &f;
+    let h = g.tanh();
+    h.backward();
+
+    ptree::print_tree(&h).expect("Print tree error!");
+    h.print_tree(TreeOptions::default().precision(3).mark_shared(true))
+        .expect("Print tree error!");
+
+    let x1: Scalar<f64> = Scalar::new(2.0);
+    let x2 = Scalar::new(0.0);
+
+    let w1 = Scalar::new(-3.0);
+    let w2 = Scalar::new(1.0);
+
+    let b = Scalar::new(6.881_373_587_019_543);
+
+    let x1w1 = &x1 * &w1;
+    let x2w2 = &x2 * &w2;
+    let x1w1x2w2 = &x1w1 + &x2w2;
+
+    let n = &x1w1x2w2 + &b;
+    let o = n.powi(2);
+    let p = o.tanh();
+
+    p.backward();
+
+    ptree::print_tree(&o).expect("Print tree error!");
+
+    let mut rng = Rng::new(1337);
+    let model: MLP = MLP::new(3, &[4, 4, 1], Activation::Tanh, &mut rng);
+    let x: Vec<Scalar> = [2.0, 3.0, -1.0].into_iter().map(Scalar::constant).collect();
+    let y = &model.forward(&x)[0];
+    y.backward();
+    println!("{}", model);
+    println!("{} parameters, y = {}", model.parameters().len(), y);
+
+    let xs = [
+        [2.0, 3.0, -1.0],
+        [3.0, -1.0, 0.5],
+        [0.5, 1.0, 1.0],
+        [1.0, 1.0, -1.0],
+    ];
+    let ys = [1.0, -1.0, -1.0, 1.0];
+    let mut optimizer = SGD::new(model.parameters(), 0.05).momentum(0.5);
+    for epoch in 0..20 {
+        let pred: Vec<Scalar> = xs
+            .iter()
+            .map(|x| {
+                let x: Vec<Scalar> = x.iter().copied().map(Scalar::constant).collect();
+                model.forward(&x).remove(0)
+            })
+            .collect();
+        let loss = loss::mse(&pred, &ys, Reduction::Mean).remove(0);
+
+        optimizer.zero_grad();
+        loss.backward();
+        optimizer.step();
+        println!("epoch {} loss {}", epoch, loss.val());
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use crate::float::Float;
+use crate::grad_mode::no_grad;
+use crate::scalar::Scalar;
+
+/// Gradients of `output` with respect to each of `inputs`.
+///
+/// With `create_graph` the gradients are built as nodes of the graph, so they
+/// can be differentiated again, e.g. for Hessian-vector products, gradient
+/// penalties or Newton steps. Otherwise they are constant leaves. Unlike
+/// [`Scalar::backward`] the `grad` fields of the graph are left untouched.
+/// Inputs `output` does not depend on get a gradient of 0.
+///
+/// # Panics
+///
+/// With `create_graph`, if the graph contains a custom operation which does
+/// not implement [`Op::backward_graph`](crate::op::Op::backward_graph).
+pub fn grad<T: Float>(
+    output: &Scalar<T>,
+    inputs: &[&Scalar<T>],
+    create_graph: bool,
+) -> Vec<Scalar<T>> {
+    let _guard = (!create_graph).then(no_grad);
+
+    let mut grads: HashMap<usize, Scalar<T>> = HashMap::new();
+    grads.insert(output.id(), Scalar::constant(T::one()));
+
+    for node in output.topological_order(Scalar::requires_grad).iter().rev() {
+        let Some(grad) = grads.get(&node.id()).cloned() else {
+            continue;
+        };
+
+        let parent_grads = node.op().backward_graph(node.parents(), node, &grad);
+        for (parent, parent_grad) in node.parents().iter().zip(parent_grads) {
+            if !parent.requires_grad() {
+                continue;
+            }
+            let sum = match grads.remove(&parent.id()) {
+                Some(acc) => acc + parent_grad,
+                None => parent_grad,
+            };
+            grads.insert(parent.id(), sum);
+        }
+    }
+
+    inputs
+        .iter()
+        .map(|input| {
+            grads
+                .get(&input.id())
+                .cloned()
+                .unwrap_or_else(|| Scalar::constant(T::zero()))
+        })
+        .collect()
+}
+
+/// Dense Jacobian of the vector valued `f` at `inputs`, row `i` holds the
+/// gradient of the `i`-th output.
+pub fn jacobian<T: Float>(f: impl Fn(&[Scalar<T>]) -> Vec<Scalar<T>>, inputs: &[T]) -> Vec<Vec<T>> {
+    let inputs: Vec<Scalar<T>> = inputs.iter().map(|&val| Scalar::new(val)).collect();
+    let input_refs: Vec<&Scalar<T>> = inputs.iter().collect();
+
+    f(&inputs)
+        .iter()
+        .map(|output| {
+            grad(output, &input_refs, false)
+                .iter()
+                .map(Scalar::val)
+                .collect()
+        })
+        .collect()
+}
+
+/// Dense Hessian of the scalar valued `f` at `inputs`.
+///
+/// The gradient is built once with `create_graph` and each of its entries is
+/// differentiated again, so row `i` holds the gradient of `∂f/∂x_i`.
+///
+/// # Panics
+///
+/// If `f` uses a custom operation without
+/// [`Op::backward_graph`](crate::op::Op::backward_graph).
+pub fn hessian<T: Float>(f: impl Fn(&[Scalar<T>]) -> Scalar<T>, inputs: &[T]) -> Vec<Vec<T>> {
+    let inputs: Vec<Scalar<T>> = inputs.iter().map(|&val| Scalar::new(val)).collect();
+    let input_refs: Vec<&Scalar<T>> = inputs.iter().collect();
+
+    let output = f(&inputs);
+    grad(&output, &input_refs, true)
+        .iter()
+        .map(|partial| {
+            grad(partial, &input_refs, false)
+                .iter()
+                .map(Scalar::val)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{CustomOp, Op};
+    use crate::scalar::ScalarOp;
+    use crate::testing::{all_ops, assert_close, numeric_derivative, sample_inputs, Cube};
+
+    /// `x²y + sin(xy)`
+    fn f(x: &[Scalar<f64>]) -> Scalar<f64> {
+        let xy = &x[0] * &x[1];
+        x[0].powi(2) * &x[1] + xy.sin()
+    }
+
+    /// Hand computed Hessian of `f`.
+    fn f_hessian(x: f64, y: f64) -> [[f64; 2]; 2] {
+        let (s, c) = ((x * y).sin(), (x * y).cos());
+        let dxy = 2.0 * x + c - x * y * s;
+        [[2.0 * y - y * y * s, dxy], [dxy, -x * x * s]]
+    }
+
+    #[derive(Debug)]
+    struct Square;
+
+    impl Op<f64> for Square {
+        fn name(&self) -> String {
+            "square".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0] * inputs[0]
+        }
+
+        fn backward(&self, inputs: &[f64], _out: f64, grad: f64) -> Vec<f64> {
+            vec![2.0 * inputs[0] * grad]
+        }
+    }
+
+    /// Derivative of `op` with respect to input `i` at `inputs`.
+    fn first_derivative(op: &ScalarOp<f64>, inputs: &[f64], i: usize) -> f64 {
+        let mut grads = vec![0.0; inputs.len()];
+        op.backward(inputs, op.forward(inputs), 1.0, &mut grads);
+        grads[i]
+    }
+
+    #[test]
+    fn second_derivatives_match_finite_differences() {
+        for op in all_ops::<f64>() {
+            for index in 0..10 {
+                let vals: Vec<f64> = sample_inputs(&op, index);
+                let inputs: Vec<Scalar<f64>> = vals.iter().map(|&val| Scalar::new(val)).collect();
+                let input_refs: Vec<&Scalar<f64>> = inputs.iter().collect();
+                let out = Scalar::new_with_parents(op.forward(&vals), inputs.clone(), op.clone());
+
+                for (i, partial) in grad(&out, &input_refs, true).iter().enumerate() {
+                    let what = format!("d{}/dx{} at {:?}", op, i, vals);
+                    assert_close(partial.val(), first_derivative(&op, &vals, i), 1e-12, &what);
+
+                    for (j, second) in grad(partial, &input_refs, false).iter().enumerate() {
+                        let shifted = |x| {
+                            let mut vals = vals.clone();
+                            vals[j] = x;
+                            first_derivative(&op, &vals, i)
+                        };
+                        let numeric = numeric_derivative(shifted, vals[j], 1e-5);
+                        let what = format!("d/dx{} of {}", j, what);
+                        assert_close(second.val(), numeric, 1e-6, what);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_vector_product() {
+        let (x, y) = (Scalar::new(0.7), Scalar::new(-1.3));
+        let v = [0.4, 2.0];
+        let g = grad(&f(&[x.clone(), y.clone()]), &[&x, &y], true);
+        let gv = &g[0] * v[0] + &g[1] * v[1];
+        let hv = grad(&gv, &[&x, &y], false);
+
+        let h = f_hessian(0.7, -1.3);
+        for (i, hv) in hv.iter().enumerate() {
+            let expected = h[i][0] * v[0] + h[i][1] * v[1];
+            assert_close(hv.val(), expected, 1e-12, format!("(Hv)[{}]", i));
+        }
+    }
+
+    #[test]
+    fn gradients_can_be_differentiated_with_backward() {
+        let (x, y) = (Scalar::new(0.7), Scalar::new(-1.3));
+        let g = grad(&f(&[x.clone(), y.clone()]), &[&x, &y], true);
+        g[0].backward();
+
+        let h = f_hessian(0.7, -1.3);
+        assert_close(x.grad(), h[0][0], 1e-12, "d²f/dx²");
+        assert_close(y.grad(), h[0][1], 1e-12, "d²f/dxdy");
+    }
+
+    #[test]
+    fn jacobian_of_vector_function() {
+        // (xy, x + y², sin x)
+        let jac = jacobian(
+            |x| vec![&x[0] * &x[1], &x[0] + x[1].powi(2), x[0].sin()],
+            &[0.5, -2.0],
+        );
+        let expected = [[-2.0, 0.5], [1.0, -4.0], [0.5f64.cos(), 0.0]];
+        assert_eq!(jac.len(), expected.len());
+        for (row, expected) in jac.iter().zip(expected) {
+            for (&actual, expected) in row.iter().zip(expected) {
+                assert_close(actual, expected, 1e-12, format!("{:?}", jac));
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_of_scalar_function() {
+        let hess = hessian(f, &[0.7, -1.3]);
+        let expected = f_hessian(0.7, -1.3);
+        for (row, expected) in hess.iter().zip(expected) {
+            for (&actual, expected) in row.iter().zip(expected) {
+                assert_close(actual, expected, 1e-12, format!("{:?}", hess));
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_is_symmetric() {
+        let hess = hessian(
+            |x| (&x[0] * &x[1]).exp() * &x[2] + x[2].powi(3) / &x[0] + x[1].tanh(),
+            &[1.2, -0.4, 0.9],
+        );
+        for i in 0..3 {
+            for j in 0..i {
+                assert_close(hess[i][j], hess[j][i], 1e-12, format!("{:?}", hess));
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_of_linear_function_is_zero() {
+        let hess = hessian(|x| &x[0] * 3.0 - &x[1] * 2.0 + 0.5, &[1.0, 2.0]);
+        assert_eq!(hess, [[0.0, 0.0], [0.0, 0.0]]);
+    }
+
+    #[test]
+    fn hessian_of_custom_op() {
+        let cube = CustomOp::new(Cube);
+        let hess = hessian(|x| Scalar::apply(&cube, &[&x[0]]), &[0.6]);
+        assert_close(hess[0][0], 3.6, 1e-12, "d²x³/dx²");
+    }
+
+    #[test]
+    fn first_order_gradients_of_custom_op_without_backward_graph() {
+        let square = CustomOp::new(Square);
+        let x = Scalar::new(1.5);
+        let g = grad(&Scalar::apply(&square, &[&x]), &[&x], false);
+        assert_eq!(g[0].val(), 3.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not support create_graph")]
+    fn create_graph_through_custom_op_without_backward_graph() {
+        let square = CustomOp::new(Square);
+        hessian(|x| Scalar::apply(&square, &[&x[0]]), &[1.5]);
+    }
+}
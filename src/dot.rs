@@ -0,0 +1,107 @@
+use std::io;
+
+use crate::float::Float;
+use crate::scalar::{Scalar, ScalarOp};
+
+impl<T: Float> Scalar<T> {
+    /// Graphviz description of the graph that computed `self`, render it with
+    /// `dot -Tsvg graph.dot -o graph.svg`.
+    ///
+    /// Every value becomes one record node showing its label, value and
+    /// gradient, fed by a separate node for the operation that produced it.
+    /// Values reachable over several paths appear only once.
+    pub fn to_dot(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_dot(&mut buf)
+            .expect("writing to a Vec<u8> cannot fail");
+        String::from_utf8(buf).expect("dot output is valid utf-8")
+    }
+
+    /// Writes the output of [`Scalar::to_dot`] to `w`.
+    pub fn write_dot<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "digraph {{")?;
+        writeln!(w, "    rankdir=LR;")?;
+
+        for node in self.topological_order(|_| true) {
+            let id = node.id();
+            let label = match node.label() {
+                Some(label) => format!("{} | ", escape(&label)),
+                None => String::new(),
+            };
+            writeln!(
+                w,
+                "    \"{}\" [label=\"{{ {}val {} | grad {} }}\", shape=record];",
+                id,
+                label,
+                escape(&node.val().to_string()),
+                escape(&node.grad().to_string())
+            )?;
+
+            if *node.op() == ScalarOp::None {
+                continue;
+            }
+            writeln!(
+                w,
+                "    \"{}op\" [label=\"{}\"];",
+                id,
+                escape(&node.op().to_string())
+            )?;
+            writeln!(w, "    \"{}op\" -> \"{}\";", id, id)?;
+            for parent in node.parents() {
+                writeln!(w, "    \"{}\" -> \"{}op\";", parent.id(), id)?;
+            }
+        }
+
+        writeln!(w, "}}")
+    }
+}
+
+/// Escapes characters with a meaning in quoted strings and record labels.
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        if matches!(c, '"' | '\\' | '{' | '}' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_node_is_emitted_once() {
+        let a = Scalar::new(2.0).with_label("a");
+        let b = (&a * &a).with_label("b");
+        let c = &b + &b;
+        let dot = c.to_dot();
+
+        for node in [&a, &b, &c] {
+            let declaration = format!("    \"{}\" [", node.id());
+            assert_eq!(dot.matches(&declaration).count(), 1, "{}", dot);
+        }
+        for (parent, child, count) in [(&a, &b, 2), (&b, &c, 2)] {
+            let edge = format!("\"{}\" -> \"{}op\";", parent.id(), child.id());
+            assert_eq!(dot.matches(&edge).count(), count, "{}", dot);
+        }
+        for node in [&b, &c] {
+            let edge = format!("\"{}op\" -> \"{}\";", node.id(), node.id());
+            assert!(dot.contains(&edge), "{}", dot);
+        }
+        assert!(!dot.contains(&format!("\"{}op\"", a.id())));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let x = Scalar::new(1.0).with_label("say \"hi\" | {x}");
+        let dot = x.to_dot();
+        assert!(
+            dot.contains(r#"{ say \"hi\" \| \{x\} | val 1 | grad 0 }"#),
+            "{}",
+            dot
+        );
+    }
+}
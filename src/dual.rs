@@ -0,0 +1,404 @@
+use std::fmt;
+use std::iter;
+use std::ops;
+
+use crate::float::Float;
+use crate::scalar::ScalarOp;
+
+/// Dual number for forward mode differentiation, carrying a value and its
+/// derivative (tangent) along one direction.
+///
+/// The derivative rules are shared with [`Scalar`](crate::scalar::Scalar),
+/// so both modes agree, including the subgradients at kinks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dual<T: Float = f32> {
+    val: T,
+    tangent: T,
+}
+
+impl<T: Float> Dual<T> {
+    pub fn new(val: T, tangent: T) -> Dual<T> {
+        Dual { val, tangent }
+    }
+
+    /// Dual number with tangent 0.
+    pub fn constant(val: T) -> Dual<T> {
+        Dual::new(val, T::zero())
+    }
+
+    /// Dual number with tangent 1, the variable to differentiate for.
+    pub fn variable(val: T) -> Dual<T> {
+        Dual::new(val, T::one())
+    }
+
+    pub fn val(&self) -> T {
+        self.val
+    }
+
+    pub fn tangent(&self) -> T {
+        self.tangent
+    }
+
+    fn unary(self, op: ScalarOp<T>) -> Dual<T> {
+        let val = op.forward(&[self.val]);
+        // skipped like in `binary`, the partial may not be finite
+        if self.tangent == T::zero() {
+            return Dual::constant(val);
+        }
+        let mut tangent = [T::zero()];
+        op.backward(&[self.val], val, self.tangent, &mut tangent);
+        Dual {
+            val,
+            tangent: tangent[0],
+        }
+    }
+
+    fn binary(self, rhs: Dual<T>, op: ScalarOp<T>) -> Dual<T> {
+        let inputs = [self.val, rhs.val];
+        let val = op.forward(&inputs);
+        let mut partials = [T::zero(); 2];
+        op.backward(&inputs, val, T::one(), &mut partials);
+
+        // a side without tangent contributes nothing, even if its partial is
+        // not finite, e.g. the exponent of pow at a non-positive base
+        let tangent = partials
+            .into_iter()
+            .zip([self.tangent, rhs.tangent])
+            .filter(|&(_, tangent)| tangent != T::zero())
+            .fold(T::zero(), |acc, (partial, tangent)| acc + partial * tangent);
+        Dual { val, tangent }
+    }
+}
+
+impl<T: Float> Dual<T> {
+    pub fn tanh(self) -> Dual<T> {
+        self.unary(ScalarOp::Tanh)
+    }
+
+    pub fn powi(self, n: i32) -> Dual<T> {
+        self.unary(ScalarOp::Powi(n))
+    }
+
+    pub fn powf(self, n: T) -> Dual<T> {
+        self.unary(ScalarOp::Powf(n))
+    }
+
+    pub fn pow(self, exponent: Dual<T>) -> Dual<T> {
+        self.binary(exponent, ScalarOp::Pow)
+    }
+
+    pub fn exp(self) -> Dual<T> {
+        self.unary(ScalarOp::Exp)
+    }
+
+    pub fn ln(self) -> Dual<T> {
+        self.unary(ScalarOp::Ln)
+    }
+
+    pub fn log2(self) -> Dual<T> {
+        self.unary(ScalarOp::Log2)
+    }
+
+    pub fn log10(self) -> Dual<T> {
+        self.unary(ScalarOp::Log10)
+    }
+
+    pub fn sqrt(self) -> Dual<T> {
+        self.unary(ScalarOp::Sqrt)
+    }
+
+    pub fn recip(self) -> Dual<T> {
+        self.unary(ScalarOp::Recip)
+    }
+
+    pub fn sin(self) -> Dual<T> {
+        self.unary(ScalarOp::Sin)
+    }
+
+    pub fn cos(self) -> Dual<T> {
+        self.unary(ScalarOp::Cos)
+    }
+
+    pub fn tan(self) -> Dual<T> {
+        self.unary(ScalarOp::Tan)
+    }
+
+    pub fn abs(self) -> Dual<T> {
+        self.unary(ScalarOp::Abs)
+    }
+
+    pub fn relu(self) -> Dual<T> {
+        self.unary(ScalarOp::Relu)
+    }
+
+    pub fn leaky_relu(self, alpha: T) -> Dual<T> {
+        self.unary(ScalarOp::LeakyRelu(alpha))
+    }
+
+    pub fn sigmoid(self) -> Dual<T> {
+        self.unary(ScalarOp::Sigmoid)
+    }
+
+    pub fn gelu(self) -> Dual<T> {
+        self.unary(ScalarOp::Gelu)
+    }
+
+    pub fn silu(self) -> Dual<T> {
+        self.unary(ScalarOp::Silu)
+    }
+
+    pub fn softplus(self) -> Dual<T> {
+        self.unary(ScalarOp::Softplus)
+    }
+
+    pub fn elu(self, alpha: T) -> Dual<T> {
+        self.unary(ScalarOp::Elu(alpha))
+    }
+}
+
+/// Jacobian-vector product of `f` at `x` in direction `v`.
+///
+/// Returns the value `f(x)` and the directional derivative `∇f(x) · v`,
+/// computed in a single forward pass.
+///
+/// # Panics
+///
+/// If `x` and `v` differ in length.
+pub fn jvp<T: Float>(f: impl Fn(&[Dual<T>]) -> Dual<T>, x: &[T], v: &[T]) -> (T, T) {
+    assert_eq!(x.len(), v.len(), "point and direction differ in length");
+
+    let inputs: Vec<Dual<T>> = x.iter().zip(v).map(|(&x, &v)| Dual::new(x, v)).collect();
+    let out = f(&inputs);
+    (out.val, out.tangent)
+}
+
+impl<T: Float> From<T> for Dual<T> {
+    fn from(value: T) -> Self {
+        Dual::constant(value)
+    }
+}
+
+macro_rules! impl_dual_binop {
+    ($imp:ident, $method:ident, $op:expr) => {
+        impl<T: Float> ops::$imp for Dual<T> {
+            type Output = Dual<T>;
+
+            fn $method(self, rhs: Dual<T>) -> Self::Output {
+                self.binary(rhs, $op)
+            }
+        }
+
+        impl<T: Float> ops::$imp<T> for Dual<T> {
+            type Output = Dual<T>;
+
+            fn $method(self, rhs: T) -> Self::Output {
+                self.binary(Dual::constant(rhs), $op)
+            }
+        }
+
+        impl ops::$imp<Dual<f32>> for f32 {
+            type Output = Dual<f32>;
+
+            fn $method(self, rhs: Dual<f32>) -> Self::Output {
+                Dual::constant(self).binary(rhs, $op)
+            }
+        }
+
+        impl ops::$imp<Dual<f64>> for f64 {
+            type Output = Dual<f64>;
+
+            fn $method(self, rhs: Dual<f64>) -> Self::Output {
+                Dual::constant(self).binary(rhs, $op)
+            }
+        }
+    };
+}
+
+impl_dual_binop!(Add, add, ScalarOp::Add);
+impl_dual_binop!(Sub, sub, ScalarOp::Sub);
+impl_dual_binop!(Mul, mul, ScalarOp::Mul);
+impl_dual_binop!(Div, div, ScalarOp::Div);
+
+macro_rules! impl_dual_op_assign {
+    ($imp:ident, $method:ident, $op:ident, $op_method:ident) => {
+        impl<T: Float> ops::$imp for Dual<T> {
+            fn $method(&mut self, rhs: Dual<T>) {
+                *self = ops::$op::$op_method(*self, rhs);
+            }
+        }
+
+        impl<T: Float> ops::$imp<T> for Dual<T> {
+            fn $method(&mut self, rhs: T) {
+                *self = ops::$op::$op_method(*self, rhs);
+            }
+        }
+    };
+}
+
+impl_dual_op_assign!(AddAssign, add_assign, Add, add);
+impl_dual_op_assign!(SubAssign, sub_assign, Sub, sub);
+impl_dual_op_assign!(MulAssign, mul_assign, Mul, mul);
+impl_dual_op_assign!(DivAssign, div_assign, Div, div);
+
+impl<T: Float> ops::Neg for Dual<T> {
+    type Output = Dual<T>;
+
+    fn neg(self) -> Self::Output {
+        self.unary(ScalarOp::Neg)
+    }
+}
+
+impl<T: Float> iter::Sum for Dual<T> {
+    fn sum<I: Iterator<Item = Dual<T>>>(iter: I) -> Self {
+        iter.fold(Dual::constant(T::zero()), |acc, x| acc + x)
+    }
+}
+
+impl<T: Float> fmt::Display for Dual<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dual({}; ε{})", self.val, self.tangent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::Scalar;
+    use crate::testing::assert_close;
+
+    type Unary = (
+        &'static str,
+        fn(&Scalar<f64>) -> Scalar<f64>,
+        fn(Dual<f64>) -> Dual<f64>,
+    );
+    type Binary = (
+        &'static str,
+        fn(&Scalar<f64>, &Scalar<f64>) -> Scalar<f64>,
+        fn(Dual<f64>, Dual<f64>) -> Dual<f64>,
+    );
+
+    #[test]
+    fn unary_tangents_match_reverse_mode() {
+        let functions: [Unary; 21] = [
+            ("tanh", Scalar::tanh, Dual::tanh),
+            ("powi", |x| x.powi(3), |x| x.powi(3)),
+            ("powf", |x| x.powf(2.5), |x| x.powf(2.5)),
+            ("exp", Scalar::exp, Dual::exp),
+            ("ln", Scalar::ln, Dual::ln),
+            ("log2", Scalar::log2, Dual::log2),
+            ("log10", Scalar::log10, Dual::log10),
+            ("sqrt", Scalar::sqrt, Dual::sqrt),
+            ("recip", Scalar::recip, Dual::recip),
+            ("sin", Scalar::sin, Dual::sin),
+            ("cos", Scalar::cos, Dual::cos),
+            ("tan", Scalar::tan, Dual::tan),
+            ("abs", Scalar::abs, Dual::abs),
+            ("relu", Scalar::relu, Dual::relu),
+            ("leaky_relu", |x| x.leaky_relu(0.1), |x| x.leaky_relu(0.1)),
+            ("sigmoid", Scalar::sigmoid, Dual::sigmoid),
+            ("gelu", Scalar::gelu, Dual::gelu),
+            ("silu", Scalar::silu, Dual::silu),
+            ("softplus", Scalar::softplus, Dual::softplus),
+            ("elu", |x| x.elu(0.5), |x| x.elu(0.5)),
+            ("neg", |x| -x, |x| -x),
+        ];
+        // zero is the kink of abs, relu, leaky_relu and elu
+        let points = [-1.7, -0.3, 0.0, 0.4, 2.1];
+
+        for (name, scalar_fn, dual_fn) in functions {
+            for x in points {
+                let domain_ok =
+                    !matches!(name, "ln" | "log2" | "log10" | "sqrt" | "powf") || x > 0.0;
+                if !domain_ok || (name == "recip" && x == 0.0) {
+                    continue;
+                }
+
+                let input = Scalar::new(x);
+                let out = scalar_fn(&input);
+                out.backward();
+
+                let (val, tangent) = jvp(|x| dual_fn(x[0]), &[x], &[1.0]);
+                let what = format!("{} at {}", name, x);
+                assert_close(val, out.val(), 1e-12, &what);
+                assert_close(tangent, input.grad(), 1e-12, &what);
+            }
+        }
+    }
+
+    #[test]
+    fn binary_tangents_match_reverse_mode() {
+        let functions: [Binary; 5] = [
+            ("add", |x, y| x + y, |x, y| x + y),
+            ("sub", |x, y| x - y, |x, y| x - y),
+            ("mul", |x, y| x * y, |x, y| x * y),
+            ("div", |x, y| x / y, |x, y| x / y),
+            ("pow", Scalar::pow, Dual::pow),
+        ];
+        let points = [[1.3, -0.7], [0.4, 2.2], [2.5, 0.0], [0.7, 1.9]];
+
+        for (name, scalar_fn, dual_fn) in functions {
+            for [x, y] in points {
+                if name == "div" && y == 0.0 {
+                    continue;
+                }
+
+                let (lhs, rhs) = (Scalar::new(x), Scalar::new(y));
+                let out = scalar_fn(&lhs, &rhs);
+                out.backward();
+
+                let what = format!("{} at ({}, {})", name, x, y);
+                for (v, grad) in [([1.0, 0.0], lhs.grad()), ([0.0, 1.0], rhs.grad())] {
+                    let (val, tangent) = jvp(|x| dual_fn(x[0], x[1]), &[x, y], &v);
+                    assert_close(val, out.val(), 1e-12, &what);
+                    assert_close(tangent, grad, 1e-12, &what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pow_with_constant_exponent_matches_reverse_mode() {
+        for x in [-2.0, -0.5, 0.0, 1.5] {
+            let base = Scalar::new(x);
+            let exponent = Scalar::constant(2.0);
+            base.pow(&exponent).backward();
+
+            let (_, tangent) = jvp(
+                |x: &[Dual<f64>]| x[0].pow(Dual::constant(2.0)),
+                &[x],
+                &[1.0],
+            );
+            assert_close(tangent, base.grad(), 1e-12, format!("pow at {}", x));
+        }
+    }
+
+    #[test]
+    fn pow_with_constant_exponent_at_non_positive_base() {
+        let (val, tangent) = jvp(
+            |x: &[Dual<f64>]| x[0].pow(Dual::constant(2.0)),
+            &[-2.0],
+            &[1.0],
+        );
+        assert_eq!((val, tangent), (4.0, -4.0));
+
+        let (val, tangent) = jvp(
+            |x: &[Dual<f64>]| x[0].pow(Dual::constant(2.0)),
+            &[0.0],
+            &[1.0],
+        );
+        assert_eq!((val, tangent), (0.0, 0.0));
+    }
+
+    #[test]
+    fn constant_operands_at_singular_points() {
+        let (_, tangent) = jvp(|x| x[0] * x[1].sqrt(), &[1.0, 0.0], &[1.0, 0.0]);
+        assert_eq!(tangent, 0.0);
+
+        let (_, tangent) = jvp(|x| x[0] + x[1].ln(), &[1.0, 0.0], &[1.0, 0.0]);
+        assert_eq!(tangent, 1.0);
+
+        let (x, y) = (Scalar::new(1.0), Scalar::constant(0.0));
+        (&x * y.sqrt()).backward();
+        assert_eq!(x.grad(), 0.0);
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use std::ops;
+
+/// Numeric type a [`Scalar`](crate::scalar::Scalar) can be built on.
+///
+/// Implemented for `f32` and `f64`, other types only have to provide the
+/// arithmetic operators and the elementary functions below.
+pub trait Float:
+    Copy
+    + PartialOrd
+    + fmt::Debug
+    + fmt::Display
+    + 'static
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Neg<Output = Self>
+    + ops::AddAssign
+    + ops::SubAssign
+    + ops::MulAssign
+    + ops::DivAssign
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn from_f64(val: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn abs(self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn recip(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn exp(self) -> Self;
+    fn exp_m1(self) -> Self;
+    fn ln(self) -> Self;
+    fn ln_1p(self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn tanh(self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($t:ident) => {
+        impl Float for $t {
+            fn zero() -> Self {
+                0.0
+            }
+
+            fn one() -> Self {
+                1.0
+            }
+
+            fn from_f64(val: f64) -> Self {
+                val as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn abs(self) -> Self {
+                $t::abs(self)
+            }
+
+            fn max(self, other: Self) -> Self {
+                $t::max(self, other)
+            }
+
+            fn min(self, other: Self) -> Self {
+                $t::min(self, other)
+            }
+
+            fn recip(self) -> Self {
+                $t::recip(self)
+            }
+
+            fn sqrt(self) -> Self {
+                $t::sqrt(self)
+            }
+
+            fn powi(self, n: i32) -> Self {
+                $t::powi(self, n)
+            }
+
+            fn powf(self, n: Self) -> Self {
+                $t::powf(self, n)
+            }
+
+            fn exp(self) -> Self {
+                $t::exp(self)
+            }
+
+            fn exp_m1(self) -> Self {
+                $t::exp_m1(self)
+            }
+
+            fn ln(self) -> Self {
+                $t::ln(self)
+            }
+
+            fn ln_1p(self) -> Self {
+                $t::ln_1p(self)
+            }
+
+            fn log2(self) -> Self {
+                $t::log2(self)
+            }
+
+            fn log10(self) -> Self {
+                $t::log10(self)
+            }
+
+            fn sin(self) -> Self {
+                $t::sin(self)
+            }
+
+            fn cos(self) -> Self {
+                $t::cos(self)
+            }
+
+            fn tan(self) -> Self {
+                $t::tan(self)
+            }
+
+            fn tanh(self) -> Self {
+                $t::tanh(self)
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
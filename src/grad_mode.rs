@@ -0,0 +1,68 @@
+use std::cell::Cell;
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Guard returned by [`no_grad`], recording of the graph is enabled again
+/// when it is dropped.
+#[derive(Debug)]
+#[must_use = "graph recording is enabled again as soon as the guard is dropped"]
+pub struct NoGradGuard {
+    prev: bool,
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.prev));
+    }
+}
+
+/// Disables the recording of the computation graph on the current thread
+/// until the returned guard goes out of scope.
+///
+/// Operations evaluated in the meantime produce leaves without parents,
+/// which is all that is needed for evaluation and metrics.
+pub fn no_grad() -> NoGradGuard {
+    let prev = GRAD_ENABLED.with(|enabled| enabled.replace(false));
+    NoGradGuard { prev }
+}
+
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::Scalar;
+
+    #[test]
+    fn operations_under_no_grad_are_leaves() {
+        let x = Scalar::new(2.0);
+        {
+            let _guard = no_grad();
+            let y = &x * &x;
+            assert_eq!(y.val(), 4.0);
+            assert!(y.is_leaf());
+            assert!(!y.requires_grad());
+        }
+
+        let y = &x * &x;
+        assert!(!y.is_leaf());
+        assert!(y.requires_grad());
+    }
+
+    #[test]
+    fn nested_guards_restore_the_previous_mode() {
+        let outer = no_grad();
+        {
+            let _inner = no_grad();
+            assert!(!is_grad_enabled());
+        }
+        assert!(!is_grad_enabled());
+
+        drop(outer);
+        assert!(is_grad_enabled());
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::autograd::grad;
+use crate::float::Float;
+use crate::grad_mode::no_grad;
+use crate::scalar::Scalar;
+
+/// Comparison of the autograd and the finite difference gradient for one input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradCheckEntry<T: Float = f32> {
+    pub index: usize,
+    pub analytic: T,
+    pub numeric: T,
+    pub abs_error: T,
+    pub rel_error: T,
+    pub passed: bool,
+}
+
+/// Result of [`gradcheck`] with one entry per input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradCheckReport<T: Float = f32> {
+    pub entries: Vec<GradCheckEntry<T>>,
+    pub tol: T,
+}
+
+impl<T: Float> GradCheckReport<T> {
+    pub fn passed(&self) -> bool {
+        self.entries.iter().all(|entry| entry.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &GradCheckEntry<T>> {
+        self.entries.iter().filter(|entry| !entry.passed)
+    }
+
+    pub fn max_abs_error(&self) -> T {
+        self.entries
+            .iter()
+            .fold(T::zero(), |max, entry| max.max(entry.abs_error))
+    }
+
+    pub fn max_rel_error(&self) -> T {
+        self.entries
+            .iter()
+            .fold(T::zero(), |max, entry| max.max(entry.rel_error))
+    }
+}
+
+impl<T: Float> fmt::Display for GradCheckReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "input {}: analytic {} numeric {} abs error {} rel error {}{}",
+                entry.index,
+                entry.analytic,
+                entry.numeric,
+                entry.abs_error,
+                entry.rel_error,
+                if entry.passed { "" } else { " FAILED" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the gradient computed by autograd with a central finite
+/// difference `(f(x + eps) - f(x - eps)) / 2 eps` for every input.
+///
+/// An input passes if its absolute or its relative error is at most `tol`.
+/// Useful to validate the backward rule of a custom [`Op`](crate::op::Op).
+pub fn gradcheck<T: Float>(
+    f: impl Fn(&[Scalar<T>]) -> Scalar<T>,
+    inputs: &[T],
+    eps: T,
+    tol: T,
+) -> GradCheckReport<T> {
+    let leaves: Vec<Scalar<T>> = inputs.iter().map(|&val| Scalar::new(val)).collect();
+    let leaf_refs: Vec<&Scalar<T>> = leaves.iter().collect();
+    let analytic = grad(&f(&leaves), &leaf_refs, false);
+
+    let _guard = no_grad();
+    let eval = |index: usize, shift: T| {
+        let shifted: Vec<Scalar<T>> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, &val)| Scalar::constant(if i == index { val + shift } else { val }))
+            .collect();
+        f(&shifted).val()
+    };
+
+    let entries = analytic
+        .iter()
+        .enumerate()
+        .map(|(index, analytic)| {
+            let analytic = analytic.val();
+            let numeric = (eval(index, eps) - eval(index, -eps)) / (eps + eps);
+            let abs_error = (analytic - numeric).abs();
+            let scale = analytic.abs().max(numeric.abs());
+            let rel_error = if scale > T::zero() {
+                abs_error / scale
+            } else {
+                T::zero()
+            };
+
+            GradCheckEntry {
+                index,
+                analytic,
+                numeric,
+                abs_error,
+                rel_error,
+                passed: abs_error <= tol || rel_error <= tol,
+            }
+        })
+        .collect();
+
+    GradCheckReport { entries, tol }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{CustomOp, Op};
+    use crate::testing::assert_close;
+
+    // derivative of x³ off by a factor of two
+    #[derive(Debug)]
+    struct WrongCube;
+
+    impl Op<f64> for WrongCube {
+        fn name(&self) -> String {
+            "wrong_cube".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs[0].powi(3)
+        }
+
+        fn backward(&self, inputs: &[f64], _out: f64, grad: f64) -> Vec<f64> {
+            vec![6.0 * inputs[0].powi(2) * grad]
+        }
+    }
+
+    #[test]
+    fn correct_gradients_pass() {
+        let report = gradcheck(
+            |x| (&x[0] * &x[1]).tanh() + x[1].exp(),
+            &[0.3, -1.2],
+            1e-6,
+            1e-6,
+        );
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.failures().count(), 0);
+        assert!(report.max_rel_error() <= 1e-6);
+    }
+
+    #[test]
+    fn wrong_custom_op_is_reported() {
+        let cube = CustomOp::new(WrongCube);
+        let report = gradcheck(
+            |x| Scalar::apply(&cube, &[&x[0]]) + &x[1],
+            &[1.5, 0.2],
+            1e-6,
+            1e-6,
+        );
+        assert!(!report.passed());
+
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].index, 0);
+        assert_close(failures[0].analytic, 13.5, 1e-12, "analytic");
+        assert_close(failures[0].numeric, 6.75, 1e-6, "numeric");
+        assert!(report.to_string().contains("FAILED"));
+    }
+}
@@ -1 +1,52 @@
-mod scalar;
+//! A small autograd engine on scalar values, following
+//! [micrograd](https://github.com/karpathy/micrograd).
+
+pub mod autograd;
+mod dot;
+pub mod dual;
+pub mod float;
+pub mod grad_mode;
+pub mod gradcheck;
+pub mod loss;
+pub mod lr_scheduler;
+pub mod nn;
+pub mod op;
+pub mod optim;
+pub mod scalar;
+pub mod state;
+pub mod tape;
+#[cfg(test)]
+mod testing;
+pub mod tree;
+
+pub use autograd::{grad, hessian, jacobian};
+pub use dual::{jvp, Dual};
+pub use float::Float;
+pub use grad_mode::{is_grad_enabled, no_grad, NoGradGuard};
+pub use gradcheck::{gradcheck, GradCheckEntry, GradCheckReport};
+pub use op::{CustomOp, Op};
+pub use optim::{Adagrad, Adam, Optimizer, RMSProp, SGD};
+pub use scalar::{zero_grad, Scalar, ScalarOp};
+pub use state::{StateDict, StateError};
+pub use tape::{Tape, Var};
+pub use tree::{ScalarTree, TreeOptions};
+
+/// Everything needed to build and differentiate expressions.
+pub mod prelude {
+    pub use crate::autograd::{grad, hessian, jacobian};
+    pub use crate::dual::{jvp, Dual};
+    pub use crate::float::Float;
+    pub use crate::grad_mode::no_grad;
+    pub use crate::gradcheck::gradcheck;
+    pub use crate::loss::Reduction;
+    pub use crate::lr_scheduler::{
+        Chain, CosineAnnealingWarmRestarts, ExponentialDecay, LinearWarmup, LrSchedule,
+        LrScheduler, OneCycle, PlateauMode, ReduceOnPlateau, Sequence, StepDecay,
+    };
+    pub use crate::nn::{Activation, Layer, Module, Neuron, Rng, Sequential, MLP};
+    pub use crate::op::{CustomOp, Op};
+    pub use crate::optim::{Adagrad, Adam, Optimizer, RMSProp, SGD};
+    pub use crate::scalar::{zero_grad, Scalar, ScalarOp};
+    pub use crate::tape::{Tape, Var};
+    pub use crate::tree::TreeOptions;
+}
@@ -0,0 +1,317 @@
+use crate::float::Float;
+use crate::scalar::Scalar;
+
+/// How the losses of the individual samples are combined.
+///
+/// All loss functions return a single element for [`Reduction::Mean`] and
+/// [`Reduction::Sum`], and one element per sample for [`Reduction::None`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reduction {
+    #[default]
+    Mean,
+    Sum,
+    None,
+}
+
+impl Reduction {
+    /// Combines `losses`, an empty batch reduces to zero.
+    pub fn reduce<T: Float>(&self, losses: Vec<Scalar<T>>) -> Vec<Scalar<T>> {
+        if *self == Reduction::None {
+            return losses;
+        }
+
+        let n = losses.len();
+        let sum = match losses.into_iter().reduce(|acc, loss| acc + loss) {
+            Some(sum) => sum,
+            None => return vec![Scalar::constant(T::zero())],
+        };
+        match self {
+            Reduction::Mean => vec![sum / T::from_f64(n as f64)],
+            _ => vec![sum],
+        }
+    }
+}
+
+fn elementwise<T: Float>(
+    pred: &[Scalar<T>],
+    target: &[T],
+    reduction: Reduction,
+    loss: impl Fn(&Scalar<T>, T) -> Scalar<T>,
+) -> Vec<Scalar<T>> {
+    assert_eq!(
+        pred.len(),
+        target.len(),
+        "predictions and targets differ in length"
+    );
+
+    let losses = pred.iter().zip(target).map(|(p, &t)| loss(p, t)).collect();
+    reduction.reduce(losses)
+}
+
+/// Mean squared error `(pred - target)²`.
+///
+/// # Panics
+///
+/// If `pred` and `target` differ in length, as do all losses below.
+pub fn mse<T: Float>(pred: &[Scalar<T>], target: &[T], reduction: Reduction) -> Vec<Scalar<T>> {
+    elementwise(pred, target, reduction, |p, t| (p - t).powi(2))
+}
+
+/// Mean absolute error `|pred - target|`.
+pub fn mae<T: Float>(pred: &[Scalar<T>], target: &[T], reduction: Reduction) -> Vec<Scalar<T>> {
+    elementwise(pred, target, reduction, |p, t| (p - t).abs())
+}
+
+/// Huber loss, quadratic for errors up to `delta` and linear beyond.
+pub fn huber<T: Float>(
+    pred: &[Scalar<T>],
+    target: &[T],
+    delta: T,
+    reduction: Reduction,
+) -> Vec<Scalar<T>> {
+    let half = T::from_f64(0.5);
+    elementwise(pred, target, reduction, |p, t| {
+        let diff = p - t;
+        if diff.val().abs() <= delta {
+            diff.powi(2) * half
+        } else {
+            (diff.abs() - half * delta) * delta
+        }
+    })
+}
+
+/// Binary cross-entropy of probabilities `pred` in `[0, 1]` and targets in
+/// `[0, 1]`.
+///
+/// Terms weighted by zero are left out, so a prediction of exactly 0 or 1
+/// has zero loss for the matching hard target. A saturated prediction for
+/// the opposite target still gives an infinite loss, prefer
+/// [`bce_with_logits`] on the raw outputs.
+pub fn bce<T: Float>(pred: &[Scalar<T>], target: &[T], reduction: Reduction) -> Vec<Scalar<T>> {
+    elementwise(pred, target, reduction, |p, t| {
+        let one = T::one();
+        if t == T::zero() {
+            -(-p + one).ln()
+        } else if t == one {
+            -p.ln()
+        } else {
+            -(p.ln() * t + (-p + one).ln() * (one - t))
+        }
+    })
+}
+
+/// Binary cross-entropy of `sigmoid(logits)`, computed as
+/// `softplus(x) - x * target` which stays finite for large logits.
+pub fn bce_with_logits<T: Float>(
+    logits: &[Scalar<T>],
+    target: &[T],
+    reduction: Reduction,
+) -> Vec<Scalar<T>> {
+    elementwise(logits, target, reduction, |x, t| x.softplus() - x * t)
+}
+
+/// Hinge loss `max(0, 1 - target * pred)` for targets of -1 or 1.
+pub fn hinge<T: Float>(pred: &[Scalar<T>], target: &[T], reduction: Reduction) -> Vec<Scalar<T>> {
+    elementwise(pred, target, reduction, |p, t| (-(p * t) + T::one()).relu())
+}
+
+/// `ln(Σ exp(x))`, shifted by the maximum so large inputs do not overflow.
+///
+/// # Panics
+///
+/// If `x` is empty.
+pub fn log_sum_exp<T: Float>(x: &[Scalar<T>]) -> Scalar<T> {
+    let max = x
+        .iter()
+        .map(Scalar::val)
+        .reduce(|a, b| a.max(b))
+        .expect("log_sum_exp of an empty slice");
+
+    let sum = x
+        .iter()
+        .map(|x| (x - max).exp())
+        .reduce(|acc, x| acc + x)
+        .expect("log_sum_exp of an empty slice");
+    sum.ln() + max
+}
+
+/// Cross-entropy of the softmax of `logits` for every sample and the index
+/// of its target class.
+///
+/// # Panics
+///
+/// If `logits` and `target` differ in length or a target class is out of
+/// range.
+pub fn cross_entropy<T: Float>(
+    logits: &[Vec<Scalar<T>>],
+    target: &[usize],
+    reduction: Reduction,
+) -> Vec<Scalar<T>> {
+    assert_eq!(
+        logits.len(),
+        target.len(),
+        "logits and targets differ in length"
+    );
+
+    let losses = logits
+        .iter()
+        .zip(target)
+        .map(|(logits, &class)| log_sum_exp(logits) - &logits[class])
+        .collect();
+    reduction.reduce(losses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck::gradcheck;
+    use crate::testing::assert_close;
+
+    type Loss<'a> = Box<dyn Fn(&[Scalar<f64>]) -> Scalar<f64> + 'a>;
+
+    fn scalars(vals: &[f64]) -> Vec<Scalar<f64>> {
+        vals.iter().map(|&val| Scalar::new(val)).collect()
+    }
+
+    fn vals(losses: &[Scalar<f64>]) -> Vec<f64> {
+        losses.iter().map(Scalar::val).collect()
+    }
+
+    #[test]
+    fn reductions() {
+        let pred = scalars(&[1.0, 2.0, 4.0]);
+        let target = [0.0, 2.0, 2.0];
+
+        assert_eq!(vals(&mse(&pred, &target, Reduction::None)), [1.0, 0.0, 4.0]);
+        assert_eq!(vals(&mse(&pred, &target, Reduction::Sum)), [5.0]);
+        assert_close(
+            mse(&pred, &target, Reduction::Mean)[0].val(),
+            5.0 / 3.0,
+            1e-12,
+            "mse(&pred, &target, Reduction::Mean)[0].val()",
+        );
+
+        let empty = mse::<f64>(&[], &[], Reduction::Mean);
+        assert_eq!(vals(&empty), [0.0]);
+        assert!(!empty[0].requires_grad());
+        assert!(mse::<f64>(&[], &[], Reduction::None).is_empty());
+    }
+
+    #[test]
+    fn loss_values() {
+        let pred = scalars(&[1.0, 2.0, 4.0]);
+        let target = [0.0, 2.0, 2.0];
+        assert_eq!(vals(&mae(&pred, &target, Reduction::None)), [1.0, 0.0, 2.0]);
+        assert_eq!(
+            vals(&huber(&pred, &target, 1.0, Reduction::None)),
+            [0.5, 0.0, 1.5]
+        );
+
+        let pred = scalars(&[0.5, 2.0, -0.5]);
+        assert_eq!(
+            vals(&hinge(&pred, &[1.0, 1.0, 1.0], Reduction::None)),
+            [0.5, 0.0, 1.5]
+        );
+
+        let pred = scalars(&[0.8, 0.3]);
+        let losses = vals(&bce(&pred, &[1.0, 0.5], Reduction::None));
+        assert_close(losses[0], -(0.8f64.ln()), 1e-12, "losses[0]");
+        assert_close(
+            losses[1],
+            -(0.5 * 0.3f64.ln() + 0.5 * 0.7f64.ln()),
+            1e-12,
+            "losses[1]",
+        );
+
+        let logits = scalars(&[0.0, 2.0]);
+        let losses = vals(&bce_with_logits(&logits, &[1.0, 0.0], Reduction::None));
+        assert_close(losses[0], 2.0f64.ln(), 1e-12, "losses[0]");
+        assert_close(losses[1], (1.0 + 2.0f64.exp()).ln(), 1e-12, "losses[1]");
+
+        let logits = vec![scalars(&[1.0, 2.0, 3.0]), scalars(&[0.0, 0.0, 0.0])];
+        let losses = vals(&cross_entropy(&logits, &[2, 0], Reduction::None));
+        let lse = (1.0f64.exp() + 2.0f64.exp() + 3.0f64.exp()).ln();
+        assert_close(losses[0], lse - 3.0, 1e-12, "losses[0]");
+        assert_close(losses[1], 3.0f64.ln(), 1e-12, "losses[1]");
+    }
+
+    #[test]
+    fn bce_of_saturated_matching_predictions_is_zero() {
+        let pred = scalars(&[0.0, 1.0]);
+        let loss = bce(&pred, &[0.0, 1.0], Reduction::Sum);
+        assert_eq!(loss[0].val(), 0.0);
+
+        loss[0].backward();
+        assert!(pred.iter().all(|p| p.grad().is_finite()));
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let (eps, tol) = (1e-6, 1e-6);
+        let target = [0.3, -1.2, 2.0];
+        let pred = [0.9, -0.4, 1.1];
+        let probs = [0.2, 0.65, 0.9];
+        let labels = [0.0, 0.4, 1.0];
+
+        let checks: [(&str, Loss, &[f64]); 7] = [
+            (
+                "mse",
+                Box::new(|x| mse(x, &target, Reduction::Mean).remove(0)),
+                &pred,
+            ),
+            (
+                "mae",
+                Box::new(|x| mae(x, &target, Reduction::Mean).remove(0)),
+                &pred,
+            ),
+            (
+                "huber",
+                Box::new(|x| huber(x, &target, 1.0, Reduction::Sum).remove(0)),
+                &pred,
+            ),
+            (
+                "bce",
+                Box::new(|x| bce(x, &labels, Reduction::Mean).remove(0)),
+                &probs,
+            ),
+            (
+                "bce_with_logits",
+                Box::new(|x| bce_with_logits(x, &labels, Reduction::Mean).remove(0)),
+                &pred,
+            ),
+            (
+                "hinge",
+                Box::new(|x| hinge(x, &[1.0, -1.0, 1.0], Reduction::Sum).remove(0)),
+                &pred,
+            ),
+            (
+                "cross_entropy",
+                Box::new(|x| cross_entropy(&[x.to_vec()], &[1], Reduction::Mean).remove(0)),
+                &pred,
+            ),
+        ];
+
+        for (name, f, inputs) in checks {
+            let report = gradcheck(f, inputs, eps, tol);
+            assert!(report.passed(), "{}:\n{}", name, report);
+        }
+    }
+
+    #[test]
+    fn log_sum_exp_does_not_overflow() {
+        let x = scalars(&[1000.0, 1000.0]);
+        let lse = log_sum_exp(&x);
+        assert_close(lse.val(), 1000.0 + 2.0f64.ln(), 1e-12, "lse.val()");
+
+        lse.backward();
+        assert_close(x[0].grad(), 0.5, 1e-12, "x[0].grad()");
+        assert_close(x[1].grad(), 0.5, 1e-12, "x[1].grad()");
+
+        let lse = log_sum_exp(&scalars(&[-1000.0, -1000.0]));
+        assert_close(lse.val(), -1000.0 + 2.0f64.ln(), 1e-12, "lse.val()");
+
+        let logits = vec![scalars(&[1000.0, 0.0])];
+        let loss = cross_entropy(&logits, &[0], Reduction::Mean);
+        assert!(loss[0].val().is_finite() && loss[0].val() < 1e-12);
+    }
+}
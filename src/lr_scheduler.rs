@@ -0,0 +1,853 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::float::Float;
+use crate::optim::Optimizer;
+use crate::state::{StateDict, StateError};
+
+/// Policy for the learning rate, as a multiple of the base learning rate of
+/// an optimizer.
+///
+/// Schedules are driven by an [`LrScheduler`] and can be combined with
+/// [`Chain`] and [`Sequence`].
+pub trait LrSchedule<T: Float = f32>: fmt::Debug {
+    /// Multiplier of the base learning rate at the current step.
+    fn factor(&self) -> T;
+
+    /// Moves on to the next step, usually called once per epoch.
+    fn advance(&mut self);
+
+    /// Reports the monitored metric, e.g. the validation loss. Ignored by
+    /// schedules only depending on the step.
+    fn observe(&mut self, _metric: T) {}
+
+    fn state_dict(&self) -> StateDict;
+
+    /// Restores a state saved with [`LrSchedule::state_dict`]. Nothing is
+    /// changed if an error is returned.
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError>;
+}
+
+/// Applies an [`LrSchedule`] to the learning rate of an optimizer.
+///
+/// ```ignore
+/// let mut scheduler = LrScheduler::new(&mut optimizer, StepDecay::new(30, 0.1));
+/// for epoch in 0..100 {
+///     train(&mut optimizer);
+///     scheduler.step(&mut optimizer);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LrScheduler<T: Float = f32> {
+    base_lr: T,
+    schedule: Box<dyn LrSchedule<T>>,
+}
+
+impl<T: Float> LrScheduler<T> {
+    /// Takes the current learning rate of `optimizer` as base learning rate
+    /// and sets the learning rate of the first step.
+    pub fn new<S: LrSchedule<T> + 'static>(
+        optimizer: &mut dyn Optimizer<T>,
+        schedule: S,
+    ) -> LrScheduler<T> {
+        let scheduler = LrScheduler {
+            base_lr: optimizer.lr(),
+            schedule: Box::new(schedule),
+        };
+        optimizer.set_lr(scheduler.lr());
+        scheduler
+    }
+
+    pub fn base_lr(&self) -> T {
+        self.base_lr
+    }
+
+    /// Learning rate of the current step.
+    pub fn lr(&self) -> T {
+        self.base_lr * self.schedule.factor()
+    }
+
+    /// Reports the monitored metric to the schedule, call it before
+    /// [`LrScheduler::step`].
+    pub fn observe(&mut self, metric: T) {
+        self.schedule.observe(metric);
+    }
+
+    /// Advances the schedule and updates the learning rate of `optimizer`.
+    pub fn step(&mut self, optimizer: &mut dyn Optimizer<T>) {
+        self.schedule.advance();
+        optimizer.set_lr(self.lr());
+    }
+
+    pub fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("base_lr", self.base_lr.to_f64());
+        state.merge("schedule", &self.schedule.state_dict());
+        state
+    }
+
+    pub fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let base_lr = state.value("base_lr")?;
+        self.schedule.load_state_dict(&state.sub_dict("schedule"))?;
+        self.base_lr = T::from_f64(base_lr);
+        Ok(())
+    }
+}
+
+fn load_count(state: &StateDict, key: &str) -> Result<usize, StateError> {
+    state.value(key).map(|count| count as usize)
+}
+
+/// Loads the entries prefixed by the index of every schedule. If one fails,
+/// the schedules loaded before it are restored, so nothing changes on errors.
+fn load_children<T: Float>(
+    mut schedules: Vec<&mut dyn LrSchedule<T>>,
+    state: &StateDict,
+) -> Result<(), StateError> {
+    let saved: Vec<StateDict> = schedules.iter().map(|s| s.state_dict()).collect();
+    let result = schedules
+        .iter_mut()
+        .enumerate()
+        .try_for_each(|(i, schedule)| {
+            schedule
+                .load_state_dict(&state.sub_dict(&i.to_string()))
+                .map_err(|err| (i, err))
+        });
+
+    let Err((failed, err)) = result else {
+        return Ok(());
+    };
+    for (schedule, saved) in schedules.iter_mut().zip(&saved).take(failed) {
+        schedule
+            .load_state_dict(saved)
+            .expect("schedule rejects its own state");
+    }
+    Err(err)
+}
+
+/// Cosine interpolation from `start` at `pct = 0` to `end` at `pct = 1`.
+fn cosine<T: Float>(start: T, end: T, pct: f64) -> T {
+    end + (start - end) * T::from_f64((1.0 + (PI * pct).cos()) / 2.0)
+}
+
+/// Multiplies the learning rate by `gamma` every `step_size` steps.
+#[derive(Debug, Clone)]
+pub struct StepDecay<T: Float = f32> {
+    step_size: usize,
+    gamma: T,
+    step: usize,
+}
+
+impl<T: Float> StepDecay<T> {
+    /// # Panics
+    ///
+    /// If `step_size` is 0.
+    pub fn new(step_size: usize, gamma: T) -> StepDecay<T> {
+        assert!(step_size > 0, "step size must be positive");
+        StepDecay {
+            step_size,
+            gamma,
+            step: 0,
+        }
+    }
+}
+
+impl<T: Float> LrSchedule<T> for StepDecay<T> {
+    fn factor(&self) -> T {
+        self.gamma.powi((self.step / self.step_size) as i32)
+    }
+
+    fn advance(&mut self) {
+        self.step += 1;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("step", self.step as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        self.step = load_count(state, "step")?;
+        Ok(())
+    }
+}
+
+/// Multiplies the learning rate by `gamma` every step.
+#[derive(Debug, Clone)]
+pub struct ExponentialDecay<T: Float = f32> {
+    gamma: T,
+    step: usize,
+}
+
+impl<T: Float> ExponentialDecay<T> {
+    pub fn new(gamma: T) -> ExponentialDecay<T> {
+        ExponentialDecay { gamma, step: 0 }
+    }
+}
+
+impl<T: Float> LrSchedule<T> for ExponentialDecay<T> {
+    fn factor(&self) -> T {
+        self.gamma.powi(self.step as i32)
+    }
+
+    fn advance(&mut self) {
+        self.step += 1;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("step", self.step as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        self.step = load_count(state, "step")?;
+        Ok(())
+    }
+}
+
+/// Cosine annealing from the base learning rate down to `min_factor` times
+/// of it, restarting after every period. Each period is `period_mult` times
+/// longer than the previous one (SGDR).
+#[derive(Debug, Clone)]
+pub struct CosineAnnealingWarmRestarts<T: Float = f32> {
+    period_mult: usize,
+    min_factor: T,
+    cycle_step: usize,
+    cycle_len: usize,
+}
+
+impl<T: Float> CosineAnnealingWarmRestarts<T> {
+    /// Restarts every `period` steps and anneals down to 0.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is 0.
+    pub fn new(period: usize) -> CosineAnnealingWarmRestarts<T> {
+        assert!(period > 0, "period must be positive");
+        CosineAnnealingWarmRestarts {
+            period_mult: 1,
+            min_factor: T::zero(),
+            cycle_step: 0,
+            cycle_len: period,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `period_mult` is 0.
+    pub fn period_mult(mut self, period_mult: usize) -> CosineAnnealingWarmRestarts<T> {
+        assert!(period_mult > 0, "period multiplier must be positive");
+        self.period_mult = period_mult;
+        self
+    }
+
+    pub fn min_factor(mut self, min_factor: T) -> CosineAnnealingWarmRestarts<T> {
+        self.min_factor = min_factor;
+        self
+    }
+}
+
+impl<T: Float> LrSchedule<T> for CosineAnnealingWarmRestarts<T> {
+    fn factor(&self) -> T {
+        let pct = self.cycle_step as f64 / self.cycle_len as f64;
+        cosine(T::one(), self.min_factor, pct)
+    }
+
+    fn advance(&mut self) {
+        self.cycle_step += 1;
+        if self.cycle_step >= self.cycle_len {
+            self.cycle_step = 0;
+            self.cycle_len *= self.period_mult;
+        }
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("cycle_step", self.cycle_step as f64);
+        state.insert_value("cycle_len", self.cycle_len as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let cycle_step = load_count(state, "cycle_step")?;
+        let cycle_len = load_count(state, "cycle_len")?;
+
+        self.cycle_step = cycle_step;
+        self.cycle_len = cycle_len.max(1);
+        Ok(())
+    }
+}
+
+/// Increases the learning rate linearly from `start_factor` times the base
+/// learning rate to the base learning rate over `warmup_steps` steps, and
+/// keeps it constant afterwards.
+#[derive(Debug, Clone)]
+pub struct LinearWarmup<T: Float = f32> {
+    start_factor: T,
+    warmup_steps: usize,
+    step: usize,
+}
+
+impl<T: Float> LinearWarmup<T> {
+    pub fn new(start_factor: T, warmup_steps: usize) -> LinearWarmup<T> {
+        LinearWarmup {
+            start_factor,
+            warmup_steps,
+            step: 0,
+        }
+    }
+}
+
+impl<T: Float> LrSchedule<T> for LinearWarmup<T> {
+    fn factor(&self) -> T {
+        if self.step >= self.warmup_steps {
+            return T::one();
+        }
+        let pct = T::from_f64(self.step as f64 / self.warmup_steps as f64);
+        self.start_factor + (T::one() - self.start_factor) * pct
+    }
+
+    fn advance(&mut self) {
+        self.step += 1;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("step", self.step as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        self.step = load_count(state, "step")?;
+        Ok(())
+    }
+}
+
+/// One cycle policy: anneals from `1 / div_factor` times the base learning
+/// rate up to the base learning rate during the first `pct_start` of
+/// `total_steps`, then down to `1 / (div_factor * final_div_factor)` times
+/// of it. The base learning rate is the maximum learning rate.
+#[derive(Debug, Clone)]
+pub struct OneCycle<T: Float = f32> {
+    total_steps: usize,
+    pct_start: f64,
+    div_factor: T,
+    final_div_factor: T,
+    step: usize,
+}
+
+impl<T: Float> OneCycle<T> {
+    /// One cycle with `pct_start` 0.3, `div_factor` 25 and
+    /// `final_div_factor` 1e4.
+    pub fn new(total_steps: usize) -> OneCycle<T> {
+        OneCycle {
+            total_steps,
+            pct_start: 0.3,
+            div_factor: T::from_f64(25.0),
+            final_div_factor: T::from_f64(1e4),
+            step: 0,
+        }
+    }
+
+    pub fn pct_start(mut self, pct_start: f64) -> OneCycle<T> {
+        self.pct_start = pct_start;
+        self
+    }
+
+    pub fn div_factor(mut self, div_factor: T) -> OneCycle<T> {
+        self.div_factor = div_factor;
+        self
+    }
+
+    pub fn final_div_factor(mut self, final_div_factor: T) -> OneCycle<T> {
+        self.final_div_factor = final_div_factor;
+        self
+    }
+}
+
+impl<T: Float> LrSchedule<T> for OneCycle<T> {
+    fn factor(&self) -> T {
+        let initial = self.div_factor.recip();
+        let min = initial / self.final_div_factor;
+        let warmup_steps = (self.pct_start * self.total_steps as f64).round() as usize;
+
+        if self.step < warmup_steps {
+            cosine(initial, T::one(), self.step as f64 / warmup_steps as f64)
+        } else if self.step < self.total_steps {
+            let pct = (self.step - warmup_steps) as f64 / (self.total_steps - warmup_steps) as f64;
+            cosine(T::one(), min, pct)
+        } else {
+            min
+        }
+    }
+
+    fn advance(&mut self) {
+        self.step += 1;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("step", self.step as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        self.step = load_count(state, "step")?;
+        Ok(())
+    }
+}
+
+/// Whether a smaller or a larger monitored metric is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlateauMode {
+    Min,
+    Max,
+}
+
+/// Multiplies the learning rate by `gamma` once the observed metric did not
+/// improve for more than `patience` steps.
+#[derive(Debug, Clone)]
+pub struct ReduceOnPlateau<T: Float = f32> {
+    mode: PlateauMode,
+    gamma: T,
+    patience: usize,
+    threshold: T,
+    cooldown: usize,
+    min_factor: T,
+    factor: T,
+    best: Option<T>,
+    num_bad_steps: usize,
+    cooldown_counter: usize,
+}
+
+impl<T: Float> ReduceOnPlateau<T> {
+    /// Minimizes the metric with `gamma` 0.1, patience 10, a relative
+    /// threshold of 1e-4 and no cooldown.
+    pub fn new() -> ReduceOnPlateau<T> {
+        ReduceOnPlateau {
+            mode: PlateauMode::Min,
+            gamma: T::from_f64(0.1),
+            patience: 10,
+            threshold: T::from_f64(1e-4),
+            cooldown: 0,
+            min_factor: T::zero(),
+            factor: T::one(),
+            best: None,
+            num_bad_steps: 0,
+            cooldown_counter: 0,
+        }
+    }
+
+    pub fn mode(mut self, mode: PlateauMode) -> ReduceOnPlateau<T> {
+        self.mode = mode;
+        self
+    }
+
+    pub fn gamma(mut self, gamma: T) -> ReduceOnPlateau<T> {
+        self.gamma = gamma;
+        self
+    }
+
+    pub fn patience(mut self, patience: usize) -> ReduceOnPlateau<T> {
+        self.patience = patience;
+        self
+    }
+
+    /// Relative improvement needed to count as better.
+    pub fn threshold(mut self, threshold: T) -> ReduceOnPlateau<T> {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Number of steps after a reduction during which bad steps are ignored.
+    pub fn cooldown(mut self, cooldown: usize) -> ReduceOnPlateau<T> {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn min_factor(mut self, min_factor: T) -> ReduceOnPlateau<T> {
+        self.min_factor = min_factor;
+        self
+    }
+
+    fn is_better(&self, metric: T) -> bool {
+        let one = T::one();
+        match (self.best, self.mode) {
+            (None, _) => true,
+            (Some(best), PlateauMode::Min) => metric < best * (one - self.threshold),
+            (Some(best), PlateauMode::Max) => metric > best * (one + self.threshold),
+        }
+    }
+}
+
+impl<T: Float> Default for ReduceOnPlateau<T> {
+    fn default() -> Self {
+        ReduceOnPlateau::new()
+    }
+}
+
+impl<T: Float> LrSchedule<T> for ReduceOnPlateau<T> {
+    fn factor(&self) -> T {
+        self.factor
+    }
+
+    fn advance(&mut self) {}
+
+    fn observe(&mut self, metric: T) {
+        if self.is_better(metric) {
+            self.best = Some(metric);
+            self.num_bad_steps = 0;
+        } else {
+            self.num_bad_steps += 1;
+        }
+
+        if self.cooldown_counter > 0 {
+            self.cooldown_counter -= 1;
+            self.num_bad_steps = 0;
+        }
+
+        if self.num_bad_steps > self.patience {
+            self.factor = (self.factor * self.gamma).max(self.min_factor);
+            self.cooldown_counter = self.cooldown;
+            self.num_bad_steps = 0;
+        }
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("factor", self.factor.to_f64());
+        state.insert("best", self.best.iter().map(|best| best.to_f64()).collect());
+        state.insert_value("num_bad_steps", self.num_bad_steps as f64);
+        state.insert_value("cooldown_counter", self.cooldown_counter as f64);
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let factor = state.value("factor")?;
+        let best = match state.get("best") {
+            Some(best) if best.len() <= 1 => best.first().map(|&best| T::from_f64(best)),
+            Some(best) => {
+                return Err(StateError::Length {
+                    key: "best".to_string(),
+                    expected: 1,
+                    found: best.len(),
+                })
+            }
+            None => return Err(StateError::Missing("best".to_string())),
+        };
+        let num_bad_steps = load_count(state, "num_bad_steps")?;
+        let cooldown_counter = load_count(state, "cooldown_counter")?;
+
+        self.factor = T::from_f64(factor);
+        self.best = best;
+        self.num_bad_steps = num_bad_steps;
+        self.cooldown_counter = cooldown_counter;
+        Ok(())
+    }
+}
+
+/// Runs several schedules at once and multiplies their factors, e.g. a
+/// warmup on top of a plateau schedule.
+#[derive(Debug, Default)]
+pub struct Chain<T: Float = f32> {
+    schedules: Vec<Box<dyn LrSchedule<T>>>,
+}
+
+impl<T: Float> Chain<T> {
+    pub fn new() -> Chain<T> {
+        Chain {
+            schedules: Vec::new(),
+        }
+    }
+
+    pub fn with<S: LrSchedule<T> + 'static>(mut self, schedule: S) -> Chain<T> {
+        self.schedules.push(Box::new(schedule));
+        self
+    }
+}
+
+impl<T: Float> LrSchedule<T> for Chain<T> {
+    fn factor(&self) -> T {
+        self.schedules
+            .iter()
+            .fold(T::one(), |factor, schedule| factor * schedule.factor())
+    }
+
+    fn advance(&mut self) {
+        for schedule in &mut self.schedules {
+            schedule.advance();
+        }
+    }
+
+    fn observe(&mut self, metric: T) {
+        for schedule in &mut self.schedules {
+            schedule.observe(metric);
+        }
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        for (i, schedule) in self.schedules.iter().enumerate() {
+            state.merge(&i.to_string(), &schedule.state_dict());
+        }
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let schedules = self.schedules.iter_mut().map(|s| &mut **s as _).collect();
+        load_children(schedules, state)
+    }
+}
+
+/// Runs schedules one after another, each for its number of steps. The last
+/// schedule keeps running once all others are done, e.g. a linear warmup
+/// followed by cosine annealing.
+#[derive(Debug, Default)]
+pub struct Sequence<T: Float = f32> {
+    phases: Vec<(Box<dyn LrSchedule<T>>, usize)>,
+    phase: usize,
+    phase_step: usize,
+}
+
+impl<T: Float> Sequence<T> {
+    pub fn new() -> Sequence<T> {
+        Sequence {
+            phases: Vec::new(),
+            phase: 0,
+            phase_step: 0,
+        }
+    }
+
+    /// Appends `schedule` running for `steps` steps.
+    pub fn then<S: LrSchedule<T> + 'static>(mut self, schedule: S, steps: usize) -> Sequence<T> {
+        self.phases.push((Box::new(schedule), steps));
+        self
+    }
+}
+
+impl<T: Float> LrSchedule<T> for Sequence<T> {
+    fn factor(&self) -> T {
+        self.phases
+            .get(self.phase)
+            .map_or(T::one(), |(schedule, _)| schedule.factor())
+    }
+
+    fn advance(&mut self) {
+        let last = self.phases.len().saturating_sub(1);
+        let Some((schedule, steps)) = self.phases.get_mut(self.phase) else {
+            return;
+        };
+
+        self.phase_step += 1;
+        if self.phase_step >= *steps && self.phase < last {
+            self.phase += 1;
+            self.phase_step = 0;
+        } else {
+            schedule.advance();
+        }
+    }
+
+    fn observe(&mut self, metric: T) {
+        if let Some((schedule, _)) = self.phases.get_mut(self.phase) {
+            schedule.observe(metric);
+        }
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("phase", self.phase as f64);
+        state.insert_value("phase_step", self.phase_step as f64);
+        for (i, (schedule, _)) in self.phases.iter().enumerate() {
+            state.merge(&i.to_string(), &schedule.state_dict());
+        }
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let phase = load_count(state, "phase")?;
+        let phase_step = load_count(state, "phase_step")?;
+        // an empty sequence stays in phase 0
+        if phase >= self.phases.len().max(1) {
+            return Err(StateError::Invalid("phase".to_string()));
+        }
+        let schedules = self.phases.iter_mut().map(|(s, _)| &mut **s as _).collect();
+        load_children(schedules, state)?;
+
+        self.phase = phase;
+        self.phase_step = phase_step;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optim::SGD;
+    use crate::testing::assert_close;
+
+    /// Factors of the first `n` steps.
+    fn factors(schedule: &mut dyn LrSchedule<f64>, n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|_| {
+                let factor = schedule.factor();
+                schedule.advance();
+                factor
+            })
+            .collect()
+    }
+
+    fn assert_all_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (&a, &e)) in actual.iter().zip(expected).enumerate() {
+            assert_close(a, e, 1e-12, format!("step {} of {:?}", i, actual));
+        }
+    }
+
+    #[test]
+    fn step_and_exponential_decay() {
+        let mut step = StepDecay::new(2, 0.5);
+        assert_all_close(&factors(&mut step, 6), &[1.0, 1.0, 0.5, 0.5, 0.25, 0.25]);
+
+        let mut exponential = ExponentialDecay::new(0.5);
+        assert_all_close(&factors(&mut exponential, 4), &[1.0, 0.5, 0.25, 0.125]);
+    }
+
+    #[test]
+    fn cosine_annealing_warm_restarts() {
+        let mut cosine = CosineAnnealingWarmRestarts::new(4);
+        let (high, low) = (
+            (1.0 + (PI / 4.0).cos()) / 2.0,
+            (1.0 + (0.75 * PI).cos()) / 2.0,
+        );
+        assert_all_close(&factors(&mut cosine, 6), &[1.0, high, 0.5, low, 1.0, high]);
+
+        // periods of 2 and 4 steps
+        let mut cosine = CosineAnnealingWarmRestarts::new(2)
+            .period_mult(2)
+            .min_factor(0.1);
+        assert_all_close(
+            &factors(&mut cosine, 7),
+            &[1.0, 0.55, 1.0, 0.1 + 0.9 * high, 0.55, 0.1 + 0.9 * low, 1.0],
+        );
+    }
+
+    #[test]
+    fn linear_warmup() {
+        let mut warmup = LinearWarmup::new(0.25, 3);
+        assert_all_close(&factors(&mut warmup, 5), &[0.25, 0.5, 0.75, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn one_cycle() {
+        // 3 warmup steps from 1 / 25 up to 1, then 7 steps down to 1 / 25e4
+        let curve = factors(&mut OneCycle::new(10), 12);
+        assert_all_close(
+            &[curve[0], curve[3], curve[10], curve[11]],
+            &[0.04, 1.0, 4e-6, 4e-6],
+        );
+        assert!(curve[..4].windows(2).all(|w| w[0] < w[1]));
+        assert!(curve[3..11].windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn reduce_on_plateau() {
+        let mut plateau = ReduceOnPlateau::new()
+            .gamma(0.5)
+            .patience(1)
+            .min_factor(0.3);
+        let mut curve = Vec::new();
+        for metric in [1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.5, 0.5] {
+            plateau.observe(metric);
+            curve.push(plateau.factor());
+        }
+        assert_all_close(&curve, &[1.0, 1.0, 0.5, 0.5, 0.5, 0.3, 0.3, 0.3]);
+
+        let mut plateau = ReduceOnPlateau::new().mode(PlateauMode::Max).patience(0);
+        plateau.observe(1.0);
+        plateau.observe(2.0);
+        assert_eq!(plateau.factor(), 1.0);
+        plateau.observe(2.0);
+        assert_close(plateau.factor(), 0.1, 1e-12, "factor");
+    }
+
+    #[test]
+    fn chain_and_sequence() {
+        let mut chain = Chain::new()
+            .with(LinearWarmup::new(0.5, 2))
+            .with(ExponentialDecay::new(0.5));
+        assert_all_close(&factors(&mut chain, 3), &[0.5, 0.75 * 0.5, 0.25]);
+
+        // the last phase keeps running after its steps
+        let mut sequence = Sequence::new()
+            .then(LinearWarmup::new(0.5, 2), 2)
+            .then(ExponentialDecay::new(0.5), 1);
+        assert_all_close(&factors(&mut sequence, 5), &[0.5, 0.75, 1.0, 0.5, 0.25]);
+    }
+
+    fn schedule() -> Sequence<f64> {
+        Sequence::new().then(LinearWarmup::new(0.1, 3), 3).then(
+            Chain::new()
+                .with(CosineAnnealingWarmRestarts::new(4).period_mult(2))
+                .with(ReduceOnPlateau::new().patience(1)),
+            100,
+        )
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let mut optimizer = SGD::new(Vec::new(), 0.1);
+        let mut scheduler = LrScheduler::new(&mut optimizer, schedule());
+        let metrics = [1.0, 0.9, 0.9, 0.9, 0.8, 0.8, 0.8, 0.8, 0.8, 0.8];
+        for &metric in &metrics[..5] {
+            scheduler.observe(metric);
+            scheduler.step(&mut optimizer);
+        }
+
+        let saved = scheduler.state_dict().to_string();
+        let mut restored_optimizer = SGD::new(Vec::new(), 0.5);
+        let mut restored = LrScheduler::new(&mut restored_optimizer, schedule());
+        restored.load_state_dict(&saved.parse().unwrap()).unwrap();
+        assert_eq!(restored.state_dict(), scheduler.state_dict());
+        assert_eq!(restored.lr(), scheduler.lr());
+
+        for &metric in &metrics[5..] {
+            scheduler.observe(metric);
+            scheduler.step(&mut optimizer);
+            restored.observe(metric);
+            restored.step(&mut restored_optimizer);
+            assert_eq!(restored_optimizer.lr(), optimizer.lr());
+        }
+    }
+
+    #[test]
+    fn failed_loads_change_nothing() {
+        let mut chain = Chain::new()
+            .with(ExponentialDecay::new(0.5))
+            .with(LinearWarmup::new(0.5, 4));
+        let mut state = StateDict::new();
+        state.insert_value("0.step", 3.0);
+        assert_eq!(
+            chain.load_state_dict(&state),
+            Err(StateError::Missing("step".to_string()))
+        );
+        assert_eq!(chain.factor(), 0.5);
+
+        let mut sequence = schedule();
+        sequence.advance();
+        let before = sequence.state_dict();
+
+        let mut state = schedule().state_dict();
+        state.insert_value("phase", 2.0);
+        assert_eq!(
+            sequence.load_state_dict(&state),
+            Err(StateError::Invalid("phase".to_string()))
+        );
+
+        let mut state = schedule().state_dict();
+        state.insert("1.1.best", vec![1.0, 2.0]);
+        assert!(sequence.load_state_dict(&state).is_err());
+        assert_eq!(sequence.state_dict(), before);
+    }
+}
@@ -0,0 +1,567 @@
+use std::fmt;
+use std::iter;
+
+use crate::float::Float;
+use crate::scalar::Scalar;
+
+/// Small pseudo random number generator (splitmix64) to initialize weights
+/// reproducibly without pulling in a dependency.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed number in `[low, high)`.
+    pub fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        // the upper 53 bits fill the mantissa of a f64 in [0, 1)
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        low + (high - low) * unit
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(42)
+    }
+}
+
+/// Nonlinearity applied to the output of a [`Neuron`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation<T: Float = f32> {
+    Identity,
+    Tanh,
+    Relu,
+    LeakyRelu(T),
+    Sigmoid,
+    Gelu,
+    Silu,
+    Softplus,
+    Elu(T),
+}
+
+impl<T: Float> Activation<T> {
+    pub fn apply(&self, x: Scalar<T>) -> Scalar<T> {
+        match self {
+            Activation::Identity => x,
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.relu(),
+            Activation::LeakyRelu(alpha) => x.leaky_relu(*alpha),
+            Activation::Sigmoid => x.sigmoid(),
+            Activation::Gelu => x.gelu(),
+            Activation::Silu => x.silu(),
+            Activation::Softplus => x.softplus(),
+            Activation::Elu(alpha) => x.elu(*alpha),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for Activation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Activation::Identity => write!(f, "Linear"),
+            Activation::Tanh => write!(f, "Tanh"),
+            Activation::Relu => write!(f, "ReLU"),
+            Activation::LeakyRelu(alpha) => write!(f, "LeakyReLU({})", alpha),
+            Activation::Sigmoid => write!(f, "Sigmoid"),
+            Activation::Gelu => write!(f, "GELU"),
+            Activation::Silu => write!(f, "SiLU"),
+            Activation::Softplus => write!(f, "Softplus"),
+            Activation::Elu(alpha) => write!(f, "ELU({})", alpha),
+        }
+    }
+}
+
+/// Building block of a model, implemented by all layers.
+///
+/// A module owns some parameters itself and may consist of child modules,
+/// [`Module::parameters`] collects the parameters of the whole tree.
+pub trait Module<T: Float = f32>: fmt::Debug {
+    fn forward(&self, x: &[Scalar<T>]) -> Vec<Scalar<T>>;
+
+    /// Parameters owned directly by this module, not by its children.
+    fn local_parameters(&self) -> Vec<(String, Scalar<T>)> {
+        Vec::new()
+    }
+
+    fn named_children(&self) -> Vec<(String, &dyn Module<T>)> {
+        Vec::new()
+    }
+
+    fn children(&self) -> Vec<&dyn Module<T>> {
+        self.named_children()
+            .into_iter()
+            .map(|(_, child)| child)
+            .collect()
+    }
+
+    /// All parameters with their path in the module tree, e.g.
+    /// `layer0.neuron1.weight2`.
+    fn named_parameters(&self) -> Vec<(String, Scalar<T>)> {
+        let mut params = self.local_parameters();
+        for (prefix, child) in self.named_children() {
+            params.extend(
+                child
+                    .named_parameters()
+                    .into_iter()
+                    .map(|(name, param)| (format!("{}.{}", prefix, name), param)),
+            );
+        }
+        params
+    }
+
+    fn parameters(&self) -> Vec<Scalar<T>> {
+        self.named_parameters()
+            .into_iter()
+            .map(|(_, param)| param)
+            .collect()
+    }
+
+    fn is_training(&self) -> bool;
+
+    /// Switches this module and all of its children between training and
+    /// evaluation mode.
+    fn set_training(&mut self, training: bool);
+
+    fn train(&mut self) {
+        self.set_training(true);
+    }
+
+    fn eval(&mut self) {
+        self.set_training(false);
+    }
+}
+
+/// Weighted sum of the inputs plus a bias, followed by an activation.
+#[derive(Debug, Clone)]
+pub struct Neuron<T: Float = f32> {
+    weights: Vec<Scalar<T>>,
+    bias: Scalar<T>,
+    activation: Activation<T>,
+    training: bool,
+}
+
+impl<T: Float> Neuron<T> {
+    /// New neuron with `nin` weights drawn uniformly from `[-1, 1)` and a
+    /// bias of 0, as in micrograd.
+    pub fn new(nin: usize, activation: Activation<T>, rng: &mut Rng) -> Neuron<T> {
+        let weights = (0..nin)
+            .map(|_| Scalar::new(T::from_f64(rng.uniform(-1.0, 1.0))))
+            .collect();
+        Neuron::from_parameters(weights, Scalar::new(T::zero()), activation)
+    }
+
+    pub fn from_parameters(
+        weights: Vec<Scalar<T>>,
+        bias: Scalar<T>,
+        activation: Activation<T>,
+    ) -> Neuron<T> {
+        Neuron {
+            weights,
+            bias,
+            activation,
+            training: true,
+        }
+    }
+
+    pub fn weights(&self) -> &[Scalar<T>] {
+        &self.weights
+    }
+
+    pub fn bias(&self) -> &Scalar<T> {
+        &self.bias
+    }
+
+    pub fn activation(&self) -> Activation<T> {
+        self.activation
+    }
+
+    /// Single output of the neuron, [`Module::forward`] wraps it in a `Vec`.
+    ///
+    /// # Panics
+    ///
+    /// If the number of inputs differs from the number of weights.
+    pub fn forward_one(&self, x: &[Scalar<T>]) -> Scalar<T> {
+        assert_eq!(
+            x.len(),
+            self.weights.len(),
+            "neuron expects {} inputs",
+            self.weights.len()
+        );
+
+        let act = self
+            .weights
+            .iter()
+            .zip(x)
+            .fold(self.bias.clone(), |acc, (w, x)| acc + w * x);
+        self.activation.apply(act)
+    }
+}
+
+impl<T: Float> Module<T> for Neuron<T> {
+    fn forward(&self, x: &[Scalar<T>]) -> Vec<Scalar<T>> {
+        vec![self.forward_one(x)]
+    }
+
+    /// Weights followed by the bias.
+    fn local_parameters(&self) -> Vec<(String, Scalar<T>)> {
+        self.weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (format!("weight{}", i), w.clone()))
+            .chain(iter::once(("bias".to_string(), self.bias.clone())))
+            .collect()
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+impl<T: Float> fmt::Display for Neuron<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}Neuron({})", self.activation, self.weights.len())
+    }
+}
+
+/// `nout` neurons sharing the same inputs.
+#[derive(Debug, Clone)]
+pub struct Layer<T: Float = f32> {
+    neurons: Vec<Neuron<T>>,
+    training: bool,
+}
+
+impl<T: Float> Layer<T> {
+    pub fn new(nin: usize, nout: usize, activation: Activation<T>, rng: &mut Rng) -> Layer<T> {
+        let neurons = (0..nout)
+            .map(|_| Neuron::new(nin, activation, rng))
+            .collect();
+        Layer::from_neurons(neurons)
+    }
+
+    pub fn from_neurons(neurons: Vec<Neuron<T>>) -> Layer<T> {
+        Layer {
+            neurons,
+            training: true,
+        }
+    }
+
+    pub fn neurons(&self) -> &[Neuron<T>] {
+        &self.neurons
+    }
+}
+
+impl<T: Float> Module<T> for Layer<T> {
+    /// One output per neuron.
+    fn forward(&self, x: &[Scalar<T>]) -> Vec<Scalar<T>> {
+        self.neurons.iter().map(|n| n.forward_one(x)).collect()
+    }
+
+    fn named_children(&self) -> Vec<(String, &dyn Module<T>)> {
+        self.neurons
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (format!("neuron{}", i), n as &dyn Module<T>))
+            .collect()
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for neuron in &mut self.neurons {
+            neuron.set_training(training);
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for Layer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Layer of [")?;
+        for (i, neuron) in self.neurons.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", neuron)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Multi layer perceptron, a stack of fully connected [`Layer`]s.
+#[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct MLP<T: Float = f32> {
+    layers: Vec<Layer<T>>,
+    training: bool,
+}
+
+impl<T: Float> MLP<T> {
+    /// MLP with `nin` inputs and layers of sizes `nouts`. Hidden layers use
+    /// `activation`, the last layer is linear as in micrograd.
+    pub fn new(nin: usize, nouts: &[usize], activation: Activation<T>, rng: &mut Rng) -> MLP<T> {
+        let sizes: Vec<usize> = iter::once(nin).chain(nouts.iter().copied()).collect();
+        let layers = sizes
+            .windows(2)
+            .enumerate()
+            .map(|(i, size)| {
+                let activation = if i + 1 == nouts.len() {
+                    Activation::Identity
+                } else {
+                    activation
+                };
+                Layer::new(size[0], size[1], activation, rng)
+            })
+            .collect();
+        MLP::from_layers(layers)
+    }
+
+    /// MLP from layers with arbitrary activations, the outputs of each layer
+    /// have to match the inputs of the next one.
+    pub fn from_layers(layers: Vec<Layer<T>>) -> MLP<T> {
+        MLP {
+            layers,
+            training: true,
+        }
+    }
+
+    pub fn layers(&self) -> &[Layer<T>] {
+        &self.layers
+    }
+}
+
+impl<T: Float> Module<T> for MLP<T> {
+    fn forward(&self, x: &[Scalar<T>]) -> Vec<Scalar<T>> {
+        self.layers
+            .iter()
+            .fold(x.to_vec(), |x, layer| layer.forward(&x))
+    }
+
+    fn named_children(&self) -> Vec<(String, &dyn Module<T>)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| (format!("layer{}", i), layer as &dyn Module<T>))
+            .collect()
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for layer in &mut self.layers {
+            layer.set_training(training);
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for MLP<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MLP of [")?;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", layer)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Chain of arbitrary modules, the outputs of each module are the inputs of
+/// the next one.
+#[derive(Debug)]
+pub struct Sequential<T: Float = f32> {
+    modules: Vec<Box<dyn Module<T>>>,
+    training: bool,
+}
+
+impl<T: Float> Sequential<T> {
+    pub fn new() -> Sequential<T> {
+        Sequential {
+            modules: Vec::new(),
+            training: true,
+        }
+    }
+
+    /// Appends `module` and returns `self`, e.g.
+    /// `Sequential::new().with(layer1).with(layer2)`.
+    pub fn with<M: Module<T> + 'static>(mut self, module: M) -> Sequential<T> {
+        self.modules.push(Box::new(module));
+        self
+    }
+}
+
+impl<T: Float> Default for Sequential<T> {
+    fn default() -> Self {
+        Sequential::new()
+    }
+}
+
+impl<T: Float> Module<T> for Sequential<T> {
+    fn forward(&self, x: &[Scalar<T>]) -> Vec<Scalar<T>> {
+        self.modules
+            .iter()
+            .fold(x.to_vec(), |x, module| module.forward(&x))
+    }
+
+    fn named_children(&self) -> Vec<(String, &dyn Module<T>)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(i, module)| (i.to_string(), module.as_ref()))
+            .collect()
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for module in &mut self.modules {
+            module.set_training(training);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neuron_forward_one_matches_module_forward() {
+        let neuron: Neuron<f64> = Neuron::new(3, Activation::Tanh, &mut Rng::new(1));
+        let x: Vec<Scalar<f64>> = [0.5, -1.0, 2.0].into_iter().map(Scalar::new).collect();
+
+        let outputs = neuron.forward(&x);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].val(), neuron.forward_one(&x).val());
+    }
+
+    #[test]
+    fn neuron_with_hand_set_weights() {
+        let weights = vec![Scalar::new(0.5), Scalar::new(-1.0)];
+        let neuron = Neuron::from_parameters(weights, Scalar::new(0.25), Activation::Tanh);
+        let x = [Scalar::new(2.0), Scalar::new(0.5)];
+
+        let out = neuron.forward_one(&x);
+        assert_eq!(out.val(), 0.75f64.tanh());
+
+        out.backward();
+        let dact = 1.0 - 0.75f64.tanh().powi(2);
+        assert_eq!(neuron.weights()[0].grad(), dact * 2.0);
+        assert_eq!(neuron.bias().grad(), dact);
+    }
+
+    #[test]
+    fn mlp_shape() {
+        let mlp: MLP<f64> = MLP::new(3, &[4, 4, 1], Activation::Tanh, &mut Rng::new(1337));
+        // 4 * (3 + 1) + 4 * (4 + 1) + 1 * (4 + 1)
+        assert_eq!(mlp.parameters().len(), 41);
+
+        let activations: Vec<Vec<Activation<f64>>> = mlp
+            .layers()
+            .iter()
+            .map(|layer| layer.neurons().iter().map(Neuron::activation).collect())
+            .collect();
+        assert_eq!(
+            activations,
+            [
+                vec![Activation::Tanh; 4],
+                vec![Activation::Tanh; 4],
+                vec![Activation::Identity]
+            ]
+        );
+
+        let x = [Scalar::new(0.5), Scalar::new(-1.0), Scalar::new(2.0)];
+        assert_eq!(mlp.forward(&x).len(), 1);
+    }
+
+    #[test]
+    fn named_parameters_follow_the_module_tree() {
+        let mlp: MLP<f64> = MLP::new(1, &[2, 1], Activation::Relu, &mut Rng::new(3));
+        let names: Vec<String> = mlp
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "layer0.neuron0.weight0",
+                "layer0.neuron0.bias",
+                "layer0.neuron1.weight0",
+                "layer0.neuron1.bias",
+                "layer1.neuron0.weight0",
+                "layer1.neuron0.weight1",
+                "layer1.neuron0.bias",
+            ]
+        );
+        let bias = mlp.layers()[1].neurons()[0].bias();
+        assert_eq!(mlp.parameters()[6].id(), bias.id());
+    }
+
+    #[test]
+    fn sequential_chains_modules() {
+        let mut rng = Rng::new(5);
+        let layer: Layer<f64> = Layer::new(2, 3, Activation::Tanh, &mut rng);
+        let mlp: MLP<f64> = MLP::new(3, &[1], Activation::Tanh, &mut rng);
+        let x = [Scalar::new(0.3), Scalar::new(-0.8)];
+        let expected = mlp.forward(&layer.forward(&x))[0].val();
+
+        let sequential = Sequential::new().with(layer).with(mlp);
+        assert_eq!(sequential.forward(&x)[0].val(), expected);
+
+        let names: Vec<String> = sequential
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names.len(), 3 * 3 + 4);
+        assert_eq!(names[0], "0.neuron0.weight0");
+        assert_eq!(names[names.len() - 1], "1.layer0.neuron0.bias");
+    }
+
+    fn all_training(module: &dyn Module<f64>, training: bool) -> bool {
+        module.is_training() == training
+            && module
+                .children()
+                .into_iter()
+                .all(|child| all_training(child, training))
+    }
+
+    #[test]
+    fn train_and_eval_reach_every_child() {
+        let mut rng = Rng::new(9);
+        let mut sequential = Sequential::new()
+            .with(Layer::new(2, 2, Activation::Relu, &mut rng))
+            .with(MLP::new(2, &[2, 1], Activation::Relu, &mut rng));
+        assert!(all_training(&sequential, true));
+
+        sequential.eval();
+        assert!(all_training(&sequential, false));
+        sequential.train();
+        assert!(all_training(&sequential, true));
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::scalar::Scalar;
+
+/// A differentiable primitive that can be plugged into the graph with
+/// [`Scalar::apply`].
+///
+/// ```
+/// use learnrustgrad::{gradcheck, CustomOp, Op, Scalar};
+///
+/// #[derive(Debug)]
+/// struct Cube;
+///
+/// impl Op for Cube {
+///     fn name(&self) -> String {
+///         "cube".to_string()
+///     }
+///
+///     fn arity(&self) -> usize {
+///         1
+///     }
+///
+///     fn forward(&self, inputs: &[f32]) -> f32 {
+///         inputs[0].powi(3)
+///     }
+///
+///     fn backward(&self, inputs: &[f32], _out: f32, grad: f32) -> Vec<f32> {
+///         vec![3.0 * inputs[0].powi(2) * grad]
+///     }
+/// }
+///
+/// let cube = CustomOp::new(Cube);
+/// let x = Scalar::new(2.0);
+/// let y = Scalar::apply(&cube, &[&x]);
+/// y.backward();
+/// assert_eq!(x.grad(), 12.0);
+///
+/// // validate the backward rule against finite differences
+/// let report = gradcheck(|x| Scalar::apply(&cube, &[&x[0]]), &[0.7], 1e-2, 1e-3);
+/// assert!(report.passed(), "{}", report);
+/// ```
+pub trait Op<T: Float = f32>: fmt::Debug {
+    /// Name used when the graph is printed.
+    fn name(&self) -> String;
+
+    /// Number of inputs the operation expects.
+    fn arity(&self) -> usize;
+
+    fn forward(&self, inputs: &[T]) -> T;
+
+    /// Gradients with respect to every input given the result `out` of the
+    /// operation and its gradient `grad`.
+    fn backward(&self, inputs: &[T], out: T, grad: T) -> Vec<T>;
+
+    /// Gradients as new nodes of the graph, used for higher order derivatives
+    /// by [`grad`](crate::autograd::grad) with `create_graph`.
+    ///
+    /// # Panics
+    ///
+    /// The default panics, as the local derivatives of [`Op::backward`] are
+    /// plain values which cannot be differentiated again.
+    fn backward_graph(
+        &self,
+        _inputs: &[Scalar<T>],
+        _out: &Scalar<T>,
+        _grad: &Scalar<T>,
+    ) -> Vec<Scalar<T>> {
+        panic!(
+            "custom op {} does not support create_graph, implement Op::backward_graph",
+            self.name()
+        )
+    }
+}
+
+/// Shared handle to a user defined [`Op`], two handles are equal if they
+/// point to the same operation.
+#[derive(Debug, Clone)]
+pub struct CustomOp<T: Float = f32>(Rc<dyn Op<T>>);
+
+impl<T: Float> CustomOp<T> {
+    pub fn new<O: Op<T> + 'static>(op: O) -> CustomOp<T> {
+        CustomOp(Rc::new(op))
+    }
+}
+
+impl<T: Float> From<Rc<dyn Op<T>>> for CustomOp<T> {
+    fn from(op: Rc<dyn Op<T>>) -> Self {
+        CustomOp(op)
+    }
+}
+
+impl<T: Float> std::ops::Deref for CustomOp<T> {
+    type Target = dyn Op<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl<T: Float> PartialEq for CustomOp<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Float> fmt::Display for CustomOp<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
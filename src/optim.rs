@@ -0,0 +1,657 @@
+use crate::float::Float;
+use crate::scalar::{self, Scalar};
+use crate::state::{StateDict, StateError};
+
+/// Updates a fixed collection of parameters from their gradients.
+///
+/// Parameters not requiring gradients are left untouched. The per parameter
+/// state, e.g. moving averages, is kept in the order of the parameters passed
+/// to the constructor.
+pub trait Optimizer<T: Float = f32> {
+    fn parameters(&self) -> &[Scalar<T>];
+
+    /// Updates every parameter once from its current gradient.
+    fn step(&mut self);
+
+    fn zero_grad(&self) {
+        scalar::zero_grad(self.parameters());
+    }
+
+    fn lr(&self) -> T;
+
+    fn set_lr(&mut self, lr: T);
+
+    /// Learning rate, step count and per parameter state.
+    fn state_dict(&self) -> StateDict;
+
+    /// Restores a state saved with [`Optimizer::state_dict`] by an optimizer
+    /// of the same kind over the same number of parameters. Nothing is
+    /// changed if an error is returned.
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError>;
+}
+
+fn to_f64<T: Float>(values: &[T]) -> Vec<f64> {
+    values.iter().map(|val| val.to_f64()).collect()
+}
+
+fn from_f64<T: Float>(values: &[f64]) -> Vec<T> {
+    values.iter().map(|&val| T::from_f64(val)).collect()
+}
+
+/// Gradient of `param` including L2 weight decay.
+fn decayed_grad<T: Float>(param: &Scalar<T>, weight_decay: T) -> T {
+    param.grad() + weight_decay * param.val()
+}
+
+/// Stochastic gradient descent with optional momentum, Nesterov momentum and
+/// weight decay.
+#[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct SGD<T: Float = f32> {
+    params: Vec<Scalar<T>>,
+    lr: T,
+    momentum: T,
+    nesterov: bool,
+    weight_decay: T,
+    velocity: Vec<T>,
+}
+
+impl<T: Float> SGD<T> {
+    pub fn new(params: Vec<Scalar<T>>, lr: T) -> SGD<T> {
+        let velocity = vec![T::zero(); params.len()];
+        SGD {
+            params,
+            lr,
+            momentum: T::zero(),
+            nesterov: false,
+            weight_decay: T::zero(),
+            velocity,
+        }
+    }
+
+    pub fn momentum(mut self, momentum: T) -> SGD<T> {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Evaluates the gradient at the point the momentum is heading to, only
+    /// has an effect with momentum.
+    pub fn nesterov(mut self, nesterov: bool) -> SGD<T> {
+        self.nesterov = nesterov;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: T) -> SGD<T> {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    pub fn velocity(&self) -> &[T] {
+        &self.velocity
+    }
+}
+
+impl<T: Float> Optimizer<T> for SGD<T> {
+    fn parameters(&self) -> &[Scalar<T>] {
+        &self.params
+    }
+
+    fn step(&mut self) {
+        for (param, velocity) in self.params.iter().zip(&mut self.velocity) {
+            if !param.requires_grad() {
+                continue;
+            }
+
+            let mut grad = decayed_grad(param, self.weight_decay);
+            if self.momentum != T::zero() {
+                *velocity = self.momentum * *velocity + grad;
+                grad = if self.nesterov {
+                    grad + self.momentum * *velocity
+                } else {
+                    *velocity
+                };
+            }
+            param.set_val(param.val() - self.lr * grad);
+        }
+    }
+
+    fn lr(&self) -> T {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: T) {
+        self.lr = lr;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("lr", self.lr.to_f64());
+        state.insert("velocity", to_f64(&self.velocity));
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let lr = state.value("lr")?;
+        let velocity = state.values("velocity", self.params.len())?;
+
+        self.lr = T::from_f64(lr);
+        self.velocity = from_f64(velocity);
+        Ok(())
+    }
+}
+
+/// Adam, adaptive moment estimation, with L2 or decoupled weight decay
+/// (AdamW).
+#[derive(Debug, Clone)]
+pub struct Adam<T: Float = f32> {
+    params: Vec<Scalar<T>>,
+    lr: T,
+    betas: (T, T),
+    eps: T,
+    weight_decay: T,
+    decoupled_weight_decay: bool,
+    step: usize,
+    exp_avg: Vec<T>,
+    exp_avg_sq: Vec<T>,
+}
+
+impl<T: Float> Adam<T> {
+    /// Adam with betas `(0.9, 0.999)`, eps `1e-8` and no weight decay.
+    pub fn new(params: Vec<Scalar<T>>, lr: T) -> Adam<T> {
+        let n = params.len();
+        Adam {
+            params,
+            lr,
+            betas: (T::from_f64(0.9), T::from_f64(0.999)),
+            eps: T::from_f64(1e-8),
+            weight_decay: T::zero(),
+            decoupled_weight_decay: false,
+            step: 0,
+            exp_avg: vec![T::zero(); n],
+            exp_avg_sq: vec![T::zero(); n],
+        }
+    }
+
+    /// AdamW, Adam with a decoupled weight decay of `0.01`.
+    pub fn adamw(params: Vec<Scalar<T>>, lr: T) -> Adam<T> {
+        Adam::new(params, lr)
+            .weight_decay(T::from_f64(0.01))
+            .decoupled_weight_decay(true)
+    }
+
+    /// Decay rates of the moving averages of the gradient and its square.
+    pub fn betas(mut self, beta1: T, beta2: T) -> Adam<T> {
+        self.betas = (beta1, beta2);
+        self
+    }
+
+    pub fn eps(mut self, eps: T) -> Adam<T> {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: T) -> Adam<T> {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    /// Shrinks the parameters directly instead of adding the weight decay to
+    /// the gradient, which makes it independent of the adaptive step size.
+    pub fn decoupled_weight_decay(mut self, decoupled: bool) -> Adam<T> {
+        self.decoupled_weight_decay = decoupled;
+        self
+    }
+
+    /// Number of steps taken so far.
+    pub fn steps(&self) -> usize {
+        self.step
+    }
+
+    /// Moving average of the gradient of every parameter.
+    pub fn exp_avg(&self) -> &[T] {
+        &self.exp_avg
+    }
+
+    /// Moving average of the squared gradient of every parameter.
+    pub fn exp_avg_sq(&self) -> &[T] {
+        &self.exp_avg_sq
+    }
+}
+
+impl<T: Float> Optimizer<T> for Adam<T> {
+    fn parameters(&self) -> &[Scalar<T>] {
+        &self.params
+    }
+
+    fn step(&mut self) {
+        self.step += 1;
+        let one = T::one();
+        let (beta1, beta2) = self.betas;
+        let bias_correction1 = one - beta1.powi(self.step as i32);
+        let bias_correction2 = one - beta2.powi(self.step as i32);
+
+        let state = self.exp_avg.iter_mut().zip(&mut self.exp_avg_sq);
+        for (param, (exp_avg, exp_avg_sq)) in self.params.iter().zip(state) {
+            if !param.requires_grad() {
+                continue;
+            }
+
+            let mut val = param.val();
+            let grad = if self.decoupled_weight_decay {
+                val -= self.lr * self.weight_decay * val;
+                param.grad()
+            } else {
+                decayed_grad(param, self.weight_decay)
+            };
+
+            *exp_avg = beta1 * *exp_avg + (one - beta1) * grad;
+            *exp_avg_sq = beta2 * *exp_avg_sq + (one - beta2) * grad * grad;
+            let denom = (*exp_avg_sq / bias_correction2).sqrt() + self.eps;
+            param.set_val(val - self.lr * (*exp_avg / bias_correction1) / denom);
+        }
+    }
+
+    fn lr(&self) -> T {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: T) {
+        self.lr = lr;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("lr", self.lr.to_f64());
+        state.insert_value("step", self.step as f64);
+        state.insert("exp_avg", to_f64(&self.exp_avg));
+        state.insert("exp_avg_sq", to_f64(&self.exp_avg_sq));
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let n = self.params.len();
+        let lr = state.value("lr")?;
+        let step = state.value("step")?;
+        let exp_avg = state.values("exp_avg", n)?;
+        let exp_avg_sq = state.values("exp_avg_sq", n)?;
+
+        self.lr = T::from_f64(lr);
+        self.step = step as usize;
+        self.exp_avg = from_f64(exp_avg);
+        self.exp_avg_sq = from_f64(exp_avg_sq);
+        Ok(())
+    }
+}
+
+/// RMSProp, scales the step by a moving average of the squared gradient.
+#[derive(Debug, Clone)]
+pub struct RMSProp<T: Float = f32> {
+    params: Vec<Scalar<T>>,
+    lr: T,
+    alpha: T,
+    eps: T,
+    weight_decay: T,
+    square_avg: Vec<T>,
+}
+
+impl<T: Float> RMSProp<T> {
+    /// RMSProp with alpha `0.99`, eps `1e-8` and no weight decay.
+    pub fn new(params: Vec<Scalar<T>>, lr: T) -> RMSProp<T> {
+        let square_avg = vec![T::zero(); params.len()];
+        RMSProp {
+            params,
+            lr,
+            alpha: T::from_f64(0.99),
+            eps: T::from_f64(1e-8),
+            weight_decay: T::zero(),
+            square_avg,
+        }
+    }
+
+    /// Decay rate of the moving average.
+    pub fn alpha(mut self, alpha: T) -> RMSProp<T> {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn eps(mut self, eps: T) -> RMSProp<T> {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: T) -> RMSProp<T> {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    pub fn square_avg(&self) -> &[T] {
+        &self.square_avg
+    }
+}
+
+impl<T: Float> Optimizer<T> for RMSProp<T> {
+    fn parameters(&self) -> &[Scalar<T>] {
+        &self.params
+    }
+
+    fn step(&mut self) {
+        let one = T::one();
+        for (param, square_avg) in self.params.iter().zip(&mut self.square_avg) {
+            if !param.requires_grad() {
+                continue;
+            }
+
+            let grad = decayed_grad(param, self.weight_decay);
+            *square_avg = self.alpha * *square_avg + (one - self.alpha) * grad * grad;
+            param.set_val(param.val() - self.lr * grad / (square_avg.sqrt() + self.eps));
+        }
+    }
+
+    fn lr(&self) -> T {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: T) {
+        self.lr = lr;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("lr", self.lr.to_f64());
+        state.insert("square_avg", to_f64(&self.square_avg));
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let lr = state.value("lr")?;
+        let square_avg = state.values("square_avg", self.params.len())?;
+
+        self.lr = T::from_f64(lr);
+        self.square_avg = from_f64(square_avg);
+        Ok(())
+    }
+}
+
+/// Adagrad, scales the step by the sum of all squared gradients so far.
+#[derive(Debug, Clone)]
+pub struct Adagrad<T: Float = f32> {
+    params: Vec<Scalar<T>>,
+    lr: T,
+    eps: T,
+    weight_decay: T,
+    sum: Vec<T>,
+}
+
+impl<T: Float> Adagrad<T> {
+    /// Adagrad with eps `1e-10` and no weight decay.
+    pub fn new(params: Vec<Scalar<T>>, lr: T) -> Adagrad<T> {
+        let sum = vec![T::zero(); params.len()];
+        Adagrad {
+            params,
+            lr,
+            eps: T::from_f64(1e-10),
+            weight_decay: T::zero(),
+            sum,
+        }
+    }
+
+    pub fn eps(mut self, eps: T) -> Adagrad<T> {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: T) -> Adagrad<T> {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    pub fn sum(&self) -> &[T] {
+        &self.sum
+    }
+}
+
+impl<T: Float> Optimizer<T> for Adagrad<T> {
+    fn parameters(&self) -> &[Scalar<T>] {
+        &self.params
+    }
+
+    fn step(&mut self) {
+        for (param, sum) in self.params.iter().zip(&mut self.sum) {
+            if !param.requires_grad() {
+                continue;
+            }
+
+            let grad = decayed_grad(param, self.weight_decay);
+            *sum += grad * grad;
+            param.set_val(param.val() - self.lr * grad / (sum.sqrt() + self.eps));
+        }
+    }
+
+    fn lr(&self) -> T {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: T) {
+        self.lr = lr;
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let mut state = StateDict::new();
+        state.insert_value("lr", self.lr.to_f64());
+        state.insert("sum", to_f64(&self.sum));
+        state
+    }
+
+    fn load_state_dict(&mut self, state: &StateDict) -> Result<(), StateError> {
+        let lr = state.value("lr")?;
+        let sum = state.values("sum", self.params.len())?;
+
+        self.lr = T::from_f64(lr);
+        self.sum = from_f64(sum);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_close;
+
+    /// Steps `optimizer` on the loss `grad * Σ params`, so every parameter
+    /// gets the gradient `grad`.
+    fn step_with_grad(optimizer: &mut dyn Optimizer<f64>, grad: f64) {
+        optimizer.zero_grad();
+        let loss: Scalar<f64> = optimizer.parameters().iter().map(|p| p * grad).sum();
+        loss.backward();
+        optimizer.step();
+    }
+
+    #[test]
+    fn sgd() {
+        let x = Scalar::new(1.0);
+        let mut sgd = SGD::new(vec![x.clone()], 0.1).weight_decay(0.1);
+        step_with_grad(&mut sgd, 0.5);
+        // gradient 0.5 + 0.1 * 1.0
+        assert_close(x.val(), 1.0 - 0.1 * 0.6, 1e-12, "x.val()");
+
+        let frozen = Scalar::constant(1.0);
+        let mut sgd = SGD::new(vec![frozen.clone()], 0.1);
+        step_with_grad(&mut sgd, 0.5);
+        assert_eq!(frozen.val(), 1.0);
+    }
+
+    #[test]
+    fn sgd_momentum() {
+        let x = Scalar::new(1.0);
+        let mut sgd = SGD::new(vec![x.clone()], 0.1).momentum(0.9);
+
+        step_with_grad(&mut sgd, 0.5);
+        assert_close(sgd.velocity()[0], 0.5, 1e-12, "sgd.velocity()[0]");
+        assert_close(x.val(), 0.95, 1e-12, "x.val()");
+
+        step_with_grad(&mut sgd, 0.5);
+        assert_close(
+            sgd.velocity()[0],
+            0.9 * 0.5 + 0.5,
+            1e-12,
+            "sgd.velocity()[0]",
+        );
+        assert_close(x.val(), 0.95 - 0.1 * 0.95, 1e-12, "x.val()");
+    }
+
+    #[test]
+    fn sgd_nesterov() {
+        let x = Scalar::new(1.0);
+        let mut sgd = SGD::new(vec![x.clone()], 0.1).momentum(0.9).nesterov(true);
+
+        // velocity 0.5, step along 0.5 + 0.9 * 0.5
+        step_with_grad(&mut sgd, 0.5);
+        assert_close(x.val(), 1.0 - 0.1 * 0.95, 1e-12, "x.val()");
+
+        // velocity 0.95, step along 0.5 + 0.9 * 0.95
+        step_with_grad(&mut sgd, 0.5);
+        assert_close(x.val(), 0.905 - 0.1 * 1.355, 1e-12, "x.val()");
+    }
+
+    #[test]
+    fn adam_bias_correction() {
+        let x = Scalar::new(1.0);
+        let mut adam = Adam::new(vec![x.clone()], 0.1);
+
+        // the corrected averages are 0.5 and 0.25 after both steps, without
+        // correction the first step would be 0.1 * 0.05 / sqrt(0.00025)
+        let update = 0.1 * 0.5 / (0.5 + 1e-8);
+        step_with_grad(&mut adam, 0.5);
+        assert_close(adam.exp_avg()[0], 0.05, 1e-12, "adam.exp_avg()[0]");
+        assert_close(adam.exp_avg_sq()[0], 0.00025, 1e-12, "adam.exp_avg_sq()[0]");
+        assert_close(x.val(), 1.0 - update, 1e-12, "x.val()");
+
+        step_with_grad(&mut adam, 0.5);
+        assert_eq!(adam.steps(), 2);
+        assert_close(adam.exp_avg()[0], 0.095, 1e-12, "adam.exp_avg()[0]");
+        assert_close(
+            adam.exp_avg_sq()[0],
+            0.00049975,
+            1e-12,
+            "adam.exp_avg_sq()[0]",
+        );
+        assert_close(x.val(), 1.0 - 2.0 * update, 1e-12, "x.val()");
+    }
+
+    #[test]
+    fn adamw_decouples_weight_decay() {
+        let x = Scalar::new(1.0);
+        let mut adamw = Adam::adamw(vec![x.clone()], 0.1);
+        step_with_grad(&mut adamw, 0.5);
+        // shrunk by lr * 0.01 first, the averages only see the gradient
+        assert_close(adamw.exp_avg()[0], 0.05, 1e-12, "adamw.exp_avg()[0]");
+        assert_close(
+            x.val(),
+            1.0 - 0.1 * 0.01 - 0.1 * 0.5 / (0.5 + 1e-8),
+            1e-12,
+            "x.val()",
+        );
+
+        let y = Scalar::new(1.0);
+        let mut adam = Adam::new(vec![y.clone()], 0.1).weight_decay(0.01);
+        step_with_grad(&mut adam, 0.5);
+        // L2 decay enters the gradient and is normalized away
+        assert_close(adam.exp_avg()[0], 0.051, 1e-12, "adam.exp_avg()[0]");
+        assert_close(y.val(), 1.0 - 0.1 * 0.51 / (0.51 + 1e-8), 1e-12, "y.val()");
+    }
+
+    #[test]
+    fn rmsprop() {
+        let x = Scalar::new(1.0);
+        let mut rmsprop = RMSProp::new(vec![x.clone()], 0.01);
+
+        step_with_grad(&mut rmsprop, 0.5);
+        assert_close(
+            rmsprop.square_avg()[0],
+            0.0025,
+            1e-12,
+            "rmsprop.square_avg()[0]",
+        );
+        assert_close(x.val(), 1.0 - 0.01 * 0.5 / (0.05 + 1e-8), 1e-12, "x.val()");
+
+        let before = x.val();
+        step_with_grad(&mut rmsprop, 0.5);
+        assert_close(
+            rmsprop.square_avg()[0],
+            0.004975,
+            1e-12,
+            "rmsprop.square_avg()[0]",
+        );
+        assert_close(
+            x.val(),
+            before - 0.01 * 0.5 / (0.004975f64.sqrt() + 1e-8),
+            1e-12,
+            "x.val()",
+        );
+    }
+
+    #[test]
+    fn adagrad() {
+        let x = Scalar::new(1.0);
+        let mut adagrad = Adagrad::new(vec![x.clone()], 0.1);
+
+        step_with_grad(&mut adagrad, 0.5);
+        assert_close(adagrad.sum()[0], 0.25, 1e-12, "adagrad.sum()[0]");
+        assert_close(x.val(), 1.0 - 0.1 * 0.5 / (0.5 + 1e-10), 1e-12, "x.val()");
+
+        let before = x.val();
+        step_with_grad(&mut adagrad, 0.5);
+        assert_close(adagrad.sum()[0], 0.5, 1e-12, "adagrad.sum()[0]");
+        assert_close(
+            x.val(),
+            before - 0.1 * 0.5 / (0.5f64.sqrt() + 1e-10),
+            1e-12,
+            "x.val()",
+        );
+    }
+
+    #[test]
+    fn state_dict_round_trip() {
+        let params = vec![Scalar::new(1.0), Scalar::new(-2.0)];
+        let mut adam = Adam::new(params.clone(), 0.1);
+        step_with_grad(&mut adam, 0.5);
+        step_with_grad(&mut adam, -0.3);
+
+        let saved = adam.state_dict().to_string();
+        let state: StateDict = saved.parse().unwrap();
+        assert_eq!(state, adam.state_dict());
+
+        let copies: Vec<Scalar<f64>> = params.iter().map(|p| Scalar::new(p.val())).collect();
+        let mut restored = Adam::new(copies.clone(), 0.5);
+        restored.load_state_dict(&state).unwrap();
+        assert_eq!(restored.lr(), 0.1);
+        assert_eq!(restored.steps(), 2);
+
+        step_with_grad(&mut adam, 0.7);
+        step_with_grad(&mut restored, 0.7);
+        for (param, copy) in params.iter().zip(&copies) {
+            assert_eq!(param.val(), copy.val());
+        }
+    }
+
+    #[test]
+    fn load_state_dict_rejects_mismatched_state() {
+        let mut sgd = SGD::new(vec![Scalar::new(1.0)], 0.1).momentum(0.9);
+        step_with_grad(&mut sgd, 0.5);
+
+        let mut state = StateDict::new();
+        state.insert_value("lr", 0.5);
+        state.insert("velocity", vec![1.0, 2.0]);
+        let err = sgd.load_state_dict(&state).unwrap_err();
+        assert_eq!(
+            err,
+            StateError::Length {
+                key: "velocity".to_string(),
+                expected: 1,
+                found: 2
+            }
+        );
+        assert_eq!(sgd.lr(), 0.1);
+        assert_eq!(sgd.velocity(), [0.5]);
+    }
+}
@@ -1,227 +1,1127 @@
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::fmt;
 use std::io;
+use std::iter;
 use std::ops;
+use std::rc::Rc;
 
 use ptree::style::Style;
 use ptree::TreeItem;
 
+use crate::float::Float;
+use crate::grad_mode;
+use crate::op::CustomOp;
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum ScalarOp {
+pub enum ScalarOp<T: Float = f32> {
     None,
+    Abs,
     Add,
+    AddConst(T),
+    Cos,
     Div,
+    Elu(T),
+    Exp,
+    Gelu,
+    LeakyRelu(T),
+    Ln,
+    Log10,
+    Log2,
     Mul,
+    MulConst(T),
+    Neg,
+    Pow,
+    Powf(T),
     Powi(i32),
+    Recip,
+    Relu,
+    Sigmoid,
+    Silu,
+    Sin,
+    Softplus,
+    Sqrt,
+    Sub,
+    Tan,
     Tanh,
+    Custom(CustomOp<T>),
 }
 
-impl fmt::Display for ScalarOp {
+impl<T: Float> fmt::Display for ScalarOp<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ScalarOp::None => write!(f, ""),
             ScalarOp::Add => write!(f, "+"),
+            ScalarOp::AddConst(c) => write!(f, "+ {}", c),
             ScalarOp::Mul => write!(f, "*"),
+            ScalarOp::MulConst(c) => write!(f, "* {}", c),
             ScalarOp::Powi(i) => write!(f, "powi({})", i),
             ScalarOp::Tanh => write!(f, "tanh"),
             ScalarOp::Div => write!(f, "/"),
+            ScalarOp::Sub => write!(f, "-"),
+            ScalarOp::Neg => write!(f, "neg"),
+            ScalarOp::Abs => write!(f, "abs"),
+            ScalarOp::Cos => write!(f, "cos"),
+            ScalarOp::Exp => write!(f, "exp"),
+            ScalarOp::Ln => write!(f, "ln"),
+            ScalarOp::Log10 => write!(f, "log10"),
+            ScalarOp::Log2 => write!(f, "log2"),
+            ScalarOp::Pow => write!(f, "pow"),
+            ScalarOp::Powf(n) => write!(f, "powf({})", n),
+            ScalarOp::Recip => write!(f, "recip"),
+            ScalarOp::Sin => write!(f, "sin"),
+            ScalarOp::Sqrt => write!(f, "sqrt"),
+            ScalarOp::Tan => write!(f, "tan"),
+            ScalarOp::Elu(alpha) => write!(f, "elu({})", alpha),
+            ScalarOp::Gelu => write!(f, "gelu"),
+            ScalarOp::LeakyRelu(alpha) => write!(f, "leaky_relu({})", alpha),
+            ScalarOp::Relu => write!(f, "relu"),
+            ScalarOp::Sigmoid => write!(f, "sigmoid"),
+            ScalarOp::Silu => write!(f, "silu"),
+            ScalarOp::Softplus => write!(f, "softplus"),
+            ScalarOp::Custom(op) => write!(f, "{}", op),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Scalar<'a> {
-    pub val: f32,
-    pub grad: RefCell<f32>,
-    lhs_parent: Option<&'a Scalar<'a>>,
-    rhs_parent: Option<&'a Scalar<'a>>,
-    op: ScalarOp,
-}
-
-impl<'a> Scalar<'a> {
-    pub fn new(val: f32) -> Scalar<'a> {
-        Scalar {
-            val,
-            grad: RefCell::new(0.0),
-            lhs_parent: None,
-            rhs_parent: None,
-            op: ScalarOp::None,
-        }
-    }
-
-    pub fn new_with_parents<'b>(
-        val: f32,
-        lhs_parent: Option<&'b Scalar>,
-        rhs_parent: Option<&'b Scalar>,
-        op: ScalarOp,
-    ) -> Scalar<'b> {
-        Scalar {
-            val,
-            grad: RefCell::new(0.0),
-            lhs_parent,
-            rhs_parent,
-            op,
+impl<T: Float> ScalarOp<T> {
+    /// Value of the operation applied to `inputs`, one value for unary and
+    /// two for binary operations.
+    pub(crate) fn forward(&self, inputs: &[T]) -> T {
+        let (zero, one, half) = (T::zero(), T::one(), T::from_f64(0.5));
+        let lhs = inputs.first().copied().unwrap_or(zero);
+        let rhs = inputs.get(1).copied().unwrap_or(zero);
+        match self {
+            ScalarOp::None => lhs,
+            ScalarOp::Add => lhs + rhs,
+            ScalarOp::AddConst(c) => lhs + *c,
+            ScalarOp::Div => lhs / rhs,
+            ScalarOp::Sub => lhs - rhs,
+            ScalarOp::Neg => -lhs,
+            ScalarOp::Mul => lhs * rhs,
+            ScalarOp::MulConst(c) => lhs * *c,
+            ScalarOp::Powi(i) => lhs.powi(*i),
+            ScalarOp::Tanh => lhs.tanh(),
+            ScalarOp::Abs => lhs.abs(),
+            ScalarOp::Cos => lhs.cos(),
+            ScalarOp::Exp => lhs.exp(),
+            ScalarOp::Ln => lhs.ln(),
+            ScalarOp::Log10 => lhs.log10(),
+            ScalarOp::Log2 => lhs.log2(),
+            ScalarOp::Pow => lhs.powf(rhs),
+            ScalarOp::Powf(n) => lhs.powf(*n),
+            ScalarOp::Recip => lhs.recip(),
+            ScalarOp::Sin => lhs.sin(),
+            ScalarOp::Sqrt => lhs.sqrt(),
+            ScalarOp::Tan => lhs.tan(),
+            ScalarOp::Elu(alpha) => {
+                if lhs > zero {
+                    lhs
+                } else {
+                    *alpha * lhs.exp_m1()
+                }
+            }
+            ScalarOp::Gelu => {
+                let (k, c) = gelu_constants::<T>();
+                half * lhs * (one + (k * (lhs + c * lhs.powi(3))).tanh())
+            }
+            ScalarOp::LeakyRelu(alpha) => {
+                if lhs > zero {
+                    lhs
+                } else {
+                    *alpha * lhs
+                }
+            }
+            ScalarOp::Relu => lhs.max(zero),
+            ScalarOp::Sigmoid => sigmoid(lhs),
+            ScalarOp::Silu => lhs * sigmoid(lhs),
+            ScalarOp::Softplus => lhs.max(zero) + (-lhs.abs()).exp().ln_1p(),
+            ScalarOp::Custom(op) => op.forward(inputs),
         }
     }
 
-    pub fn calc_grad(&self) {
-        match self.op {
-            ScalarOp::Add => {
-                let lhs = self.lhs_parent.unwrap();
-                let rhs = self.rhs_parent.unwrap();
+    /// Writes the gradients with respect to every input into `grads`, given
+    /// the result `out` of the operation and its gradient `grad`.
+    pub(crate) fn backward(&self, inputs: &[T], out: T, grad: T, grads: &mut [T]) {
+        let (zero, one, two) = (T::zero(), T::one(), T::from_f64(2.0));
+        let lhs = inputs.first().copied().unwrap_or(zero);
+        let rhs = inputs.get(1).copied().unwrap_or(zero);
+        let (dlhs, drhs) = match self {
+            ScalarOp::None => (zero, zero),
+            ScalarOp::Add => (grad, grad),
+            ScalarOp::AddConst(_) => (grad, zero),
+            ScalarOp::Mul => (grad * rhs, grad * lhs),
+            ScalarOp::MulConst(c) => (grad * *c, zero),
+            ScalarOp::Div => (grad / rhs, -grad * lhs / (rhs * rhs)),
+            ScalarOp::Sub => (grad, -grad),
+            ScalarOp::Neg => (-grad, zero),
+            ScalarOp::Tanh => ((one - out * out) * grad, zero),
+            // avoids 0 * inf at lhs = 0
+            ScalarOp::Powi(0) => (zero, zero),
+            ScalarOp::Powi(i) => (T::from_f64(*i as f64) * lhs.powi(i - 1) * grad, zero),
+            // subgradient 0 at the kink
+            ScalarOp::Abs => (sign(lhs) * grad, zero),
+            ScalarOp::Cos => (-lhs.sin() * grad, zero),
+            ScalarOp::Exp => (out * grad, zero),
+            ScalarOp::Ln => (grad / lhs, zero),
+            ScalarOp::Log10 => (grad / (lhs * T::from_f64(std::f64::consts::LN_10)), zero),
+            ScalarOp::Log2 => (grad / (lhs * T::from_f64(std::f64::consts::LN_2)), zero),
+            ScalarOp::Pow => (rhs * lhs.powf(rhs - one) * grad, out * lhs.ln() * grad),
+            ScalarOp::Powf(n) => (*n * lhs.powf(*n - one) * grad, zero),
+            ScalarOp::Recip => (-out * out * grad, zero),
+            ScalarOp::Sin => (lhs.cos() * grad, zero),
+            ScalarOp::Sqrt => (grad / (two * out), zero),
+            ScalarOp::Tan => ((one + out * out) * grad, zero),
+            // the non-differentiable points of the piecewise activations use
+            // the derivative of the left piece, e.g. relu'(0) = 0
+            ScalarOp::Elu(alpha) => {
+                if lhs > zero {
+                    (grad, zero)
+                } else {
+                    ((out + *alpha) * grad, zero)
+                }
+            }
+            ScalarOp::Gelu => {
+                let (k, c) = gelu_constants::<T>();
+                let half = T::from_f64(0.5);
+                let t = (k * (lhs + c * lhs.powi(3))).tanh();
+                let dt = (one - t * t) * k * (one + T::from_f64(3.0) * c * lhs * lhs);
+                ((half * (one + t) + half * lhs * dt) * grad, zero)
+            }
+            ScalarOp::LeakyRelu(alpha) => {
+                if lhs > zero {
+                    (grad, zero)
+                } else {
+                    (*alpha * grad, zero)
+                }
+            }
+            ScalarOp::Relu => {
+                if lhs > zero {
+                    (grad, zero)
+                } else {
+                    (zero, zero)
+                }
+            }
+            ScalarOp::Sigmoid => (out * (one - out) * grad, zero),
+            ScalarOp::Silu => {
+                let s = sigmoid(lhs);
+                ((s + lhs * s * (one - s)) * grad, zero)
+            }
+            ScalarOp::Softplus => (sigmoid(lhs) * grad, zero),
+            ScalarOp::Custom(op) => {
+                for (slot, grad) in grads.iter_mut().zip(op.backward(inputs, out, grad)) {
+                    *slot = grad;
+                }
+                return;
+            }
+        };
+        for (slot, grad) in grads.iter_mut().zip([dlhs, drhs]) {
+            *slot = grad;
+        }
+    }
+}
 
-                let old_lgrad = lhs.grad.borrow().clone();
-                *lhs.grad.borrow_mut() = old_lgrad + *self.grad.borrow();
+impl<T: Float> ScalarOp<T> {
+    /// Same as `backward`, but the gradients are built as new nodes of the
+    /// graph so they can be differentiated again. `inputs` are the parents of
+    /// `out`.
+    pub(crate) fn backward_graph(
+        &self,
+        inputs: &[Scalar<T>],
+        out: &Scalar<T>,
+        grad: &Scalar<T>,
+    ) -> Vec<Scalar<T>> {
+        let (zero, one, two) = (T::zero(), T::one(), T::from_f64(2.0));
+        if let ScalarOp::Custom(op) = self {
+            if grad_mode::is_grad_enabled() {
+                return op.backward_graph(inputs, out, grad);
+            }
+            // without create_graph the gradients are not differentiated again
+            let vals: Vec<T> = inputs.iter().map(Scalar::val).collect();
+            return op
+                .backward(&vals, out.val(), grad.val())
+                .into_iter()
+                .map(Scalar::constant)
+                .collect();
+        }
+        if inputs.is_empty() {
+            return Vec::new();
+        }
 
-                let old_rgrad = rhs.grad.borrow().clone();
-                *rhs.grad.borrow_mut() = old_rgrad + *self.grad.borrow();
+        let lhs = &inputs[0];
+        let x = lhs.val();
+        match self {
+            ScalarOp::Add => vec![grad.clone(), grad.clone()],
+            ScalarOp::AddConst(_) => vec![grad.clone()],
+            ScalarOp::Mul => vec![grad * &inputs[1], grad * lhs],
+            ScalarOp::MulConst(c) => vec![grad * *c],
+            ScalarOp::Div => {
+                let rhs = &inputs[1];
+                vec![grad / rhs, -(grad * lhs) / (rhs * rhs)]
+            }
+            ScalarOp::Sub => vec![grad.clone(), -grad],
+            ScalarOp::Neg => vec![-grad],
+            ScalarOp::Tanh => vec![grad * (-(out * out) + one)],
+            ScalarOp::Powi(0) => vec![grad * zero],
+            ScalarOp::Powi(i) => vec![grad * lhs.powi(i - 1) * T::from_f64(*i as f64)],
+            ScalarOp::Abs => vec![grad * sign(x)],
+            ScalarOp::Cos => vec![-(grad * lhs.sin())],
+            ScalarOp::Exp => vec![grad * out],
+            ScalarOp::Ln => vec![grad / lhs],
+            ScalarOp::Log10 => vec![grad / (lhs * T::from_f64(std::f64::consts::LN_10))],
+            ScalarOp::Log2 => vec![grad / (lhs * T::from_f64(std::f64::consts::LN_2))],
+            ScalarOp::Pow => {
+                let rhs = &inputs[1];
+                vec![grad * rhs * lhs.pow(&(rhs - one)), grad * out * lhs.ln()]
+            }
+            ScalarOp::Powf(n) => vec![grad * lhs.powf(*n - one) * *n],
+            ScalarOp::Recip => vec![-(grad * out * out)],
+            ScalarOp::Sin => vec![grad * lhs.cos()],
+            ScalarOp::Sqrt => vec![grad / (out * two)],
+            ScalarOp::Tan => vec![grad * (out * out + one)],
+            ScalarOp::Elu(alpha) => {
+                if x > zero {
+                    vec![grad.clone()]
+                } else {
+                    vec![grad * (out + *alpha)]
+                }
+            }
+            ScalarOp::Gelu => {
+                let (k, c) = gelu_constants::<T>();
+                let half = T::from_f64(0.5);
+                let t = ((lhs + lhs.powi(3) * c) * k).tanh();
+                let dt = (-(&t * &t) + one) * k * (lhs * lhs * (T::from_f64(3.0) * c) + one);
+                vec![grad * ((&t + one) * half + lhs * dt * half)]
             }
-            ScalarOp::Mul => {
-                let lhs = self.lhs_parent.unwrap();
-                let rhs = self.rhs_parent.unwrap();
+            ScalarOp::LeakyRelu(alpha) => {
+                if x > zero {
+                    vec![grad.clone()]
+                } else {
+                    vec![grad * *alpha]
+                }
+            }
+            ScalarOp::Relu => {
+                if x > zero {
+                    vec![grad.clone()]
+                } else {
+                    vec![grad * zero]
+                }
+            }
+            ScalarOp::Sigmoid => vec![grad * out * (-out + one)],
+            ScalarOp::Silu => {
+                let s = lhs.sigmoid();
+                vec![grad * (&s + lhs * &s * (-&s + one))]
+            }
+            ScalarOp::Softplus => vec![grad * lhs.sigmoid()],
+            ScalarOp::None | ScalarOp::Custom(_) => Vec::new(),
+        }
+    }
+}
 
-                let old_lgrad = lhs.grad.borrow().clone();
-                *lhs.grad.borrow_mut() = old_lgrad + *self.grad.borrow() / rhs.val;
+// constants of the tanh approximation of gelu, sqrt(2 / pi) and 0.044715
+fn gelu_constants<T: Float>() -> (T, T) {
+    (
+        T::from_f64((2.0 / std::f64::consts::PI).sqrt()),
+        T::from_f64(0.044_715),
+    )
+}
 
-                let old_rgrad = rhs.grad.borrow().clone();
-                *rhs.grad.borrow_mut() = old_rgrad + *self.grad.borrow() * lhs.val * rhs.val.powi(-2);
-            }
-            ScalarOp::Div => {
-                let lhs = self.lhs_parent.unwrap();
-                let rhs = self.rhs_parent.unwrap();
+fn sigmoid<T: Float>(x: T) -> T {
+    if x >= T::zero() {
+        T::one() / (T::one() + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (T::one() + e)
+    }
+}
+
+fn sign<T: Float>(x: T) -> T {
+    if x > T::zero() {
+        T::one()
+    } else if x < T::zero() {
+        -T::one()
+    } else {
+        T::zero()
+    }
+}
+
+#[derive(Debug)]
+struct Node<T: Float> {
+    val: Cell<T>,
+    grad: RefCell<T>,
+    requires_grad: Cell<bool>,
+    parents: Vec<Scalar<T>>,
+    op: ScalarOp<T>,
+    label: RefCell<Option<String>>,
+}
+
+/// Handle to a value in the computation graph.
+///
+/// Cloning a `Scalar` is cheap and yields another handle to the same node,
+/// parents are kept alive by their children. The value type defaults to
+/// `f32`, any [`Float`] can be used instead.
+#[derive(Debug, Clone)]
+pub struct Scalar<T: Float = f32>(Rc<Node<T>>);
+
+impl<T: Float> Scalar<T> {
+    /// New leaf which requires gradients, e.g. a trainable parameter.
+    pub fn new(val: T) -> Scalar<T> {
+        let leaf = Scalar::new_with_parents(val, Vec::new(), ScalarOp::None);
+        leaf.0.requires_grad.set(true);
+        leaf
+    }
+
+    /// New leaf which does not require gradients, e.g. input data.
+    pub fn constant(val: T) -> Scalar<T> {
+        Scalar::new_with_parents(val, Vec::new(), ScalarOp::None)
+    }
+
+    pub(crate) fn new_with_parents(val: T, parents: Vec<Scalar<T>>, op: ScalarOp<T>) -> Scalar<T> {
+        let (parents, op) = if grad_mode::is_grad_enabled() {
+            (parents, op)
+        } else {
+            (Vec::new(), ScalarOp::None)
+        };
+        let requires_grad = parents.iter().any(Scalar::requires_grad);
+
+        Scalar(Rc::new(Node {
+            val: Cell::new(val),
+            grad: RefCell::new(T::zero()),
+            requires_grad: Cell::new(requires_grad),
+            parents,
+            op,
+            label: RefCell::new(None),
+        }))
+    }
+
+    /// Applies a user defined operation to `inputs`.
+    ///
+    /// # Panics
+    ///
+    /// If the number of inputs does not match the arity of the operation.
+    pub fn apply(op: &CustomOp<T>, inputs: &[&Scalar<T>]) -> Scalar<T> {
+        assert_eq!(
+            inputs.len(),
+            op.arity(),
+            "{} expects {} inputs",
+            op,
+            op.arity()
+        );
+
+        let vals: Vec<T> = inputs.iter().map(|input| input.val()).collect();
+        let parents = inputs.iter().map(|&input| input.clone()).collect();
+        Scalar::new_with_parents(op.forward(&vals), parents, ScalarOp::Custom(op.clone()))
+    }
+
+    pub fn val(&self) -> T {
+        self.0.val.get()
+    }
+
+    /// Overwrites the value of a leaf, e.g. in an optimizer step. Nodes
+    /// computed from it keep their old value until they are built again.
+    ///
+    /// # Panics
+    ///
+    /// If `self` is not a leaf.
+    pub fn set_val(&self, val: T) {
+        assert!(self.is_leaf(), "only the value of leaves can be changed");
+        self.0.val.set(val);
+    }
+
+    pub fn grad(&self) -> T {
+        *self.0.grad.borrow()
+    }
+
+    pub fn op(&self) -> &ScalarOp<T> {
+        &self.0.op
+    }
+
+    /// The operands this value was computed from, empty for leaves.
+    pub fn parents(&self) -> &[Scalar<T>] {
+        &self.0.parents
+    }
 
-                let old_lgrad = lhs.grad.borrow().clone();
-                *lhs.grad.borrow_mut() = old_lgrad + *self.grad.borrow() * rhs.val;
+    pub fn is_leaf(&self) -> bool {
+        self.0.parents.is_empty()
+    }
+
+    /// Name shown when the graph is printed or exported.
+    pub fn label(&self) -> Option<String> {
+        self.0.label.borrow().clone()
+    }
+
+    pub fn set_label(&self, label: impl Into<String>) {
+        *self.0.label.borrow_mut() = Some(label.into());
+    }
+
+    /// Sets the label and returns `self`, e.g. `Scalar::new(0.5).with_label("w")`.
+    pub fn with_label(self, label: impl Into<String>) -> Scalar<T> {
+        self.set_label(label);
+        self
+    }
+
+    /// Identity of the node, shared by all handles to it.
+    pub(crate) fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as *const () as usize
+    }
 
-                let old_rgrad = rhs.grad.borrow().clone();
-                *rhs.grad.borrow_mut() = old_rgrad + *self.grad.borrow() * lhs.val;
+    /// Whether gradients are computed for this node. Leaves set it explicitly,
+    /// all other nodes require gradients if any of their parents does.
+    pub fn requires_grad(&self) -> bool {
+        self.0.requires_grad.get()
+    }
+
+    /// Freezes or unfreezes a leaf. Only nodes created afterwards see the
+    /// new flag.
+    ///
+    /// # Panics
+    ///
+    /// If `self` is not a leaf.
+    pub fn set_requires_grad(&self, requires_grad: bool) {
+        assert!(
+            self.is_leaf(),
+            "requires_grad can only be changed on leaves"
+        );
+        self.0.requires_grad.set(requires_grad);
+    }
+
+    /// New constant leaf with the value of `self`, gradients do not flow back
+    /// through it.
+    pub fn detach(&self) -> Scalar<T> {
+        Scalar::constant(self.val())
+    }
+
+    pub fn calc_grad(&self) {
+        let node = &self.0;
+        if node.parents.is_empty() {
+            return;
+        }
+
+        let vals: Vec<T> = node.parents.iter().map(Scalar::val).collect();
+        let mut grads = vec![T::zero(); vals.len()];
+        node.op.backward(&vals, self.val(), self.grad(), &mut grads);
+        for (parent, grad) in node.parents.iter().zip(grads) {
+            if parent.requires_grad() {
+                parent.add_grad(grad);
             }
-            ScalarOp::Tanh => {
-                let lhs = self.lhs_parent.unwrap();
-                let old_lgrad = lhs.grad.borrow().clone();
+        }
+    }
+
+    fn add_grad(&self, grad: T) {
+        *self.0.grad.borrow_mut() += grad;
+    }
+
+    fn unary(&self, op: ScalarOp<T>) -> Scalar<T> {
+        let val = op.forward(&[self.val()]);
+        Scalar::new_with_parents(val, vec![self.clone()], op)
+    }
+
+    fn binary(&self, rhs: &Scalar<T>, op: ScalarOp<T>) -> Scalar<T> {
+        let val = op.forward(&[self.val(), rhs.val()]);
+        Scalar::new_with_parents(val, vec![self.clone(), rhs.clone()], op)
+    }
+
+    /// Computes the gradient of `self` with respect to every node of its graph.
+    ///
+    /// Gradients of leaves accumulate over several calls, like in a training
+    /// loop with several losses, and have to be reset with [`zero_grad`] or
+    /// [`Scalar::zero_grad`]. Gradients of intermediate nodes only hold the
+    /// result of the latest backward pass. Sub-graphs which only depend on
+    /// nodes not requiring gradients are skipped.
+    ///
+    /// The gradients are plain values, use [`grad`](crate::autograd::grad)
+    /// with `create_graph` for higher order derivatives.
+    pub fn backward(&self) {
+        let topo = self.topological_order(Scalar::requires_grad);
+        for node in topo.iter().filter(|node| !node.is_leaf()) {
+            node.zero_grad();
+        }
+        *self.0.grad.borrow_mut() = T::one();
+
+        for node in topo.iter().rev() {
+            node.calc_grad();
+        }
+    }
+
+    pub fn zero_grad(&self) {
+        *self.0.grad.borrow_mut() = T::zero();
+    }
 
-                *lhs.grad.borrow_mut() = old_lgrad + (1.0 - self.val * self.val) * (*self.grad.borrow());
+    /// Resets the gradients of `self` and of all nodes it was computed from.
+    pub fn zero_grad_graph(&self) {
+        for node in self.topological_order(|_| true) {
+            node.zero_grad();
+        }
+    }
+
+    pub(crate) fn topological_order(&self, include: impl Fn(&Scalar<T>) -> bool) -> Vec<Scalar<T>> {
+        // iterative post-order DFS, every node is visited exactly once even
+        // if it is reachable over several paths. Nodes not passing `include`
+        // are skipped together with their parents.
+        let mut topo = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        if include(self) {
+            stack.push((self.clone(), false));
+        }
+
+        while let Some((node, parents_done)) = stack.pop() {
+            if parents_done {
+                topo.push(node);
+                continue;
+            }
+            if !visited.insert(Rc::as_ptr(&node.0)) {
+                continue;
             }
-            ScalarOp::Powi(i) => {
-                let lhs = self.lhs_parent.unwrap();
-                let old_lgrad = lhs.grad.borrow().clone();
 
-                *lhs.grad.borrow_mut() = old_lgrad + (lhs.val.powi(i - 1)) * (*self.grad.borrow());
+            let parents = node.0.parents.clone();
+            stack.push((node, true));
+            for parent in parents.into_iter().rev() {
+                if include(&parent) && !visited.contains(&Rc::as_ptr(&parent.0)) {
+                    stack.push((parent, false));
+                }
             }
-            _ => {}
         }
+
+        topo
     }
 }
 
-impl<'a> From<f32> for Scalar<'a> {
-    fn from(value: f32) -> Self {
+/// Resets the gradients of a collection of parameters.
+pub fn zero_grad<'a, T: Float>(params: impl IntoIterator<Item = &'a Scalar<T>>) {
+    for param in params {
+        param.zero_grad();
+    }
+}
+
+impl<T: Float> From<T> for Scalar<T> {
+    fn from(value: T) -> Self {
         Scalar::new(value)
     }
 }
 
-impl<'a> ops::Add for &'a Scalar<'a> {
-    type Output = Scalar<'a>;
+impl<T: Float> ops::Add for &Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn add(self, rhs: &Scalar<T>) -> Self::Output {
+        self.binary(rhs, ScalarOp::Add)
+    }
+}
 
-    fn add(self, rhs: &'a Scalar) -> Self::Output {
-        let out = Scalar::new_with_parents(self.val + rhs.val, Some(self), Some(rhs), ScalarOp::Add);
+impl<T: Float> ops::Add<T> for &Scalar<T> {
+    type Output = Scalar<T>;
 
-        out
+    fn add(self, rhs: T) -> Self::Output {
+        self.unary(ScalarOp::AddConst(rhs))
     }
 }
 
-impl<'a> ops::Add<f32> for &'a Scalar<'a> {
-    type Output = Scalar<'a>;
+impl<T: Float> ops::Mul for &Scalar<T> {
+    type Output = Scalar<T>;
 
-    fn add(self, rhs: f32) -> Self::Output {
-        Scalar::new_with_parents(self.val + rhs, Some(self), None, ScalarOp::Add)
+    fn mul(self, rhs: &Scalar<T>) -> Self::Output {
+        self.binary(rhs, ScalarOp::Mul)
     }
 }
 
-impl<'a> ops::Add<&'a Scalar<'a>> for f32 {
-    type Output = Scalar<'a>;
+impl<T: Float> ops::Mul<T> for &Scalar<T> {
+    type Output = Scalar<T>;
 
-    fn add(self, rhs: &'a Scalar) -> Self::Output {
-        Scalar::new_with_parents(self + rhs.val, None, Some(rhs), ScalarOp::Add)
+    fn mul(self, rhs: T) -> Self::Output {
+        self.unary(ScalarOp::MulConst(rhs))
     }
 }
 
-impl<'a> ops::Mul for &'a Scalar<'a> {
-    type Output = Scalar<'a>;
+impl<T: Float> Scalar<T> {
+    pub fn tanh(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Tanh)
+    }
+}
 
-    fn mul(self, rhs: &'a Scalar) -> Self::Output {
-        Scalar::new_with_parents(self.val * rhs.val, Some(self), Some(rhs), ScalarOp::Mul)
+impl<T: Float> Scalar<T> {
+    pub fn powi(&self, n: i32) -> Scalar<T> {
+        self.unary(ScalarOp::Powi(n))
     }
 }
 
-impl<'a> ops::Mul<f32> for &'a Scalar<'a> {
-    type Output = Scalar<'a>;
+impl<T: Float> Scalar<T> {
+    pub fn powf(&self, n: T) -> Scalar<T> {
+        self.unary(ScalarOp::Powf(n))
+    }
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        Scalar::new_with_parents(self.val * rhs, Some(self), None, ScalarOp::Mul)
+    /// `self` raised to the power `exponent`, gradients flow into both.
+    pub fn pow(&self, exponent: &Scalar<T>) -> Scalar<T> {
+        self.binary(exponent, ScalarOp::Pow)
+    }
+
+    pub fn exp(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Exp)
+    }
+
+    pub fn ln(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Ln)
+    }
+
+    pub fn log2(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Log2)
+    }
+
+    pub fn log10(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Log10)
+    }
+
+    pub fn sqrt(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Sqrt)
+    }
+
+    pub fn recip(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Recip)
+    }
+
+    pub fn sin(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Sin)
+    }
+
+    pub fn cos(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Cos)
+    }
+
+    pub fn tan(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Tan)
+    }
+
+    pub fn abs(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Abs)
     }
 }
 
-impl<'a> ops::Mul<&'a Scalar<'a>> for f32 {
-    type Output = Scalar<'a>;
+impl<T: Float> Scalar<T> {
+    /// `max(0, x)`, the gradient at 0 is 0.
+    pub fn relu(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Relu)
+    }
 
-    fn mul(self, rhs: &'a Scalar) -> Self::Output {
-        Scalar::new_with_parents(self * rhs.val, None, Some(rhs), ScalarOp::Mul)
+    /// `x` for positive `x`, `alpha * x` otherwise. The gradient at 0 is `alpha`.
+    pub fn leaky_relu(&self, alpha: T) -> Scalar<T> {
+        self.unary(ScalarOp::LeakyRelu(alpha))
+    }
+
+    pub fn sigmoid(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Sigmoid)
+    }
+
+    /// Gelu in its tanh approximation.
+    pub fn gelu(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Gelu)
+    }
+
+    /// `x * sigmoid(x)`
+    pub fn silu(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Silu)
+    }
+
+    /// `ln(1 + exp(x))`, evaluated without overflow for large `x`.
+    pub fn softplus(&self) -> Scalar<T> {
+        self.unary(ScalarOp::Softplus)
+    }
+
+    /// `x` for positive `x`, `alpha * (exp(x) - 1)` otherwise. The gradient at 0 is `alpha`.
+    pub fn elu(&self, alpha: T) -> Scalar<T> {
+        self.unary(ScalarOp::Elu(alpha))
     }
 }
 
-impl<'a> Scalar<'a> {
-    pub fn tanh(&'a self) -> Scalar<'a> {
-        Scalar::new_with_parents(self.val.tanh(), Some(self), None, ScalarOp::Tanh)
+impl<T: Float> ops::Div for &Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn div(self, rhs: &Scalar<T>) -> Self::Output {
+        self.binary(rhs, ScalarOp::Div)
     }
 }
 
-impl<'a> Scalar<'a> {
-    pub fn powi(&'a self, n: i32) -> Scalar<'a> {
-        Scalar::new_with_parents(self.val.powi(n), Some(self), None, ScalarOp::Powi(n))
+impl<T: Float> ops::Div<T> for &Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        self.unary(ScalarOp::MulConst(rhs.recip()))
     }
 }
 
-impl<'a> ops::Div for &'a Scalar<'a> {
-    type Output = Scalar<'a>;
+impl<T: Float> ops::Sub for &Scalar<T> {
+    type Output = Scalar<T>;
 
-    fn div(self, rhs: &'a Scalar) -> Self::Output {
-        Scalar::new_with_parents(self.val / rhs.val, Some(self), Some(rhs), ScalarOp::Div)
-        // could use existing operators self * &rhs.powi(-1)
-        // but &rhs.powi(-1) would be a dangling temporary variable
-        // which also have to be returned to caller, e.g. some chained expression
-        // ChainedResult<Scalar, Scalar> which could on dereferencing only return first element 
+    fn sub(self, rhs: &Scalar<T>) -> Self::Output {
+        self.binary(rhs, ScalarOp::Sub)
     }
 }
 
+impl<T: Float> ops::Sub<T> for &Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn sub(self, rhs: T) -> Self::Output {
+        self.unary(ScalarOp::AddConst(-rhs))
+    }
+}
+
+impl<T: Float> ops::Neg for &Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn neg(self) -> Self::Output {
+        self.unary(ScalarOp::Neg)
+    }
+}
+
+impl<T: Float> ops::Neg for Scalar<T> {
+    type Output = Scalar<T>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+// the owned variants only forward to the implementations on references,
+// so temporaries like `(&a + &b) * c.tanh()` can be chained
+macro_rules! forward_owned_binop {
+    ($imp:ident, $method:ident) => {
+        impl<T: Float> ops::$imp<Scalar<T>> for Scalar<T> {
+            type Output = Scalar<T>;
+
+            fn $method(self, rhs: Scalar<T>) -> Self::Output {
+                ops::$imp::$method(&self, &rhs)
+            }
+        }
+
+        impl<T: Float> ops::$imp<&Scalar<T>> for Scalar<T> {
+            type Output = Scalar<T>;
+
+            fn $method(self, rhs: &Scalar<T>) -> Self::Output {
+                ops::$imp::$method(&self, rhs)
+            }
+        }
+
+        impl<T: Float> ops::$imp<Scalar<T>> for &Scalar<T> {
+            type Output = Scalar<T>;
+
+            fn $method(self, rhs: Scalar<T>) -> Self::Output {
+                ops::$imp::$method(self, &rhs)
+            }
+        }
+
+        impl<T: Float> ops::$imp<T> for Scalar<T> {
+            type Output = Scalar<T>;
+
+            fn $method(self, rhs: T) -> Self::Output {
+                ops::$imp::$method(&self, rhs)
+            }
+        }
+    };
+}
+
+forward_owned_binop!(Add, add);
+forward_owned_binop!(Sub, sub);
+forward_owned_binop!(Mul, mul);
+forward_owned_binop!(Div, div);
+
+// a generic `impl<T> ops::Add<&Scalar<T>> for T` is not allowed, so the
+// operators with a plain number on the left are implemented per type
+macro_rules! impl_float_lhs_ops {
+    ($t:ident) => {
+        impl ops::Add<&Scalar<$t>> for $t {
+            type Output = Scalar<$t>;
+
+            fn add(self, rhs: &Scalar<$t>) -> Self::Output {
+                rhs.unary(ScalarOp::AddConst(self))
+            }
+        }
+
+        impl ops::Sub<&Scalar<$t>> for $t {
+            type Output = Scalar<$t>;
 
-impl fmt::Display for Scalar<'_> {
+            fn sub(self, rhs: &Scalar<$t>) -> Self::Output {
+                (-rhs).unary(ScalarOp::AddConst(self))
+            }
+        }
+
+        impl ops::Mul<&Scalar<$t>> for $t {
+            type Output = Scalar<$t>;
+
+            fn mul(self, rhs: &Scalar<$t>) -> Self::Output {
+                rhs.unary(ScalarOp::MulConst(self))
+            }
+        }
+
+        impl ops::Div<&Scalar<$t>> for $t {
+            type Output = Scalar<$t>;
+
+            fn div(self, rhs: &Scalar<$t>) -> Self::Output {
+                rhs.powi(-1).unary(ScalarOp::MulConst(self))
+            }
+        }
+
+        forward_owned_float_lhs_op!($t, Add, add);
+        forward_owned_float_lhs_op!($t, Sub, sub);
+        forward_owned_float_lhs_op!($t, Mul, mul);
+        forward_owned_float_lhs_op!($t, Div, div);
+    };
+}
+
+macro_rules! forward_owned_float_lhs_op {
+    ($t:ident, $imp:ident, $method:ident) => {
+        impl ops::$imp<Scalar<$t>> for $t {
+            type Output = Scalar<$t>;
+
+            fn $method(self, rhs: Scalar<$t>) -> Self::Output {
+                ops::$imp::$method(self, &rhs)
+            }
+        }
+    };
+}
+
+impl_float_lhs_ops!(f32);
+impl_float_lhs_ops!(f64);
+
+// `a += &b` replaces the handle `a` by a new node `a + b`, the old value
+// stays in the graph as parent of the new one
+macro_rules! impl_op_assign {
+    ($imp:ident, $method:ident, $op:ident, $op_method:ident) => {
+        impl<T: Float> ops::$imp<&Scalar<T>> for Scalar<T> {
+            fn $method(&mut self, rhs: &Scalar<T>) {
+                *self = ops::$op::$op_method(&*self, rhs);
+            }
+        }
+
+        impl<T: Float> ops::$imp<Scalar<T>> for Scalar<T> {
+            fn $method(&mut self, rhs: Scalar<T>) {
+                *self = ops::$op::$op_method(&*self, &rhs);
+            }
+        }
+
+        impl<T: Float> ops::$imp<T> for Scalar<T> {
+            fn $method(&mut self, rhs: T) {
+                *self = ops::$op::$op_method(&*self, rhs);
+            }
+        }
+    };
+}
+
+impl_op_assign!(AddAssign, add_assign, Add, add);
+impl_op_assign!(SubAssign, sub_assign, Sub, sub);
+impl_op_assign!(MulAssign, mul_assign, Mul, mul);
+impl_op_assign!(DivAssign, div_assign, Div, div);
+
+/// Adds up the terms without an extra leaf, an empty sum is a zero constant.
+impl<T: Float> iter::Sum for Scalar<T> {
+    fn sum<I: Iterator<Item = Scalar<T>>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc + x)
+            .unwrap_or_else(|| Scalar::constant(T::zero()))
+    }
+}
+
+impl<'a, T: Float> iter::Sum<&'a Scalar<T>> for Scalar<T> {
+    fn sum<I: Iterator<Item = &'a Scalar<T>>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+impl<T: Float> fmt::Display for Scalar<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.op != ScalarOp::None {
-            write!(f, "Val({}; Δ{})<- {}", self.val, self.grad.borrow(), self.op)
+        if self.0.op != ScalarOp::None {
+            write!(f, "Val({}; Δ{})<- {}", self.val(), self.grad(), self.0.op)
         } else {
-            write!(f, "Val({}; Δ{})", self.val, self.grad.borrow())
+            write!(f, "Val({}; Δ{})", self.val(), self.grad())
         }
     }
 }
 
-impl<'a> TreeItem for &Scalar<'a> {
+impl<T: Float> TreeItem for Scalar<T> {
     type Child = Self;
 
     fn write_self<W: io::Write>(&self, f: &mut W, style: &Style) -> io::Result<()> {
         write!(f, "{}", style.paint(self))
     }
 
-    fn children(&self) -> Cow<[Self::Child]> {
-        let mut childs = Vec::new();
+    fn children(&self) -> Cow<'_, [Self::Child]> {
+        Cow::from(self.0.parents.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dual::{jvp, Dual};
+    use crate::gradcheck::gradcheck;
+    use crate::op::Op;
+    use crate::testing::{all_ops, assert_close, sample_inputs};
+
+    #[derive(Debug)]
+    struct Product3;
+
+    impl Op<f64> for Product3 {
+        fn name(&self) -> String {
+            "product3".to_string()
+        }
+
+        fn arity(&self) -> usize {
+            3
+        }
 
-        if let Some(lhs) = self.lhs_parent {
-            childs.push(lhs);
+        fn forward(&self, inputs: &[f64]) -> f64 {
+            inputs.iter().product()
         }
-        if let Some(rhs) = self.rhs_parent {
-            childs.push(rhs);
+
+        fn backward(&self, inputs: &[f64], _out: f64, grad: f64) -> Vec<f64> {
+            let [x, y, z] = [inputs[0], inputs[1], inputs[2]];
+            vec![y * z * grad, x * z * grad, x * y * grad]
         }
+    }
+
+    #[test]
+    fn shared_node_accumulates_once_per_path() {
+        let a = Scalar::new(3.0);
+        let b = &a * &a;
+        let c = &b + &b;
+        c.backward();
+        assert_eq!(b.grad(), 2.0);
+        assert_eq!(a.grad(), 4.0 * 3.0);
+    }
+
+    #[test]
+    fn leaves_accumulate_and_intermediates_reset() {
+        let x = Scalar::new(2.0);
+        let y = &x * &x;
+        let z = &y * 3.0;
+        z.backward();
+        z.backward();
+        assert_eq!(x.grad(), 2.0 * 12.0);
+        assert_eq!(y.grad(), 3.0);
+
+        x.zero_grad();
+        z.backward();
+        assert_eq!(x.grad(), 12.0);
+
+        z.zero_grad_graph();
+        assert!([&x, &y, &z].iter().all(|node| node.grad() == 0.0));
+
+        let params = [Scalar::new(1.0), Scalar::new(2.0)];
+        (&params[0] * &params[1]).backward();
+        zero_grad(&params);
+        assert!(params.iter().all(|param| param.grad() == 0.0));
+    }
+
+    #[test]
+    fn no_gradient_flows_through_detach() {
+        let x = Scalar::new(2.0);
+        let y = &x * &x;
+        let detached = y.detach();
+        assert!(detached.is_leaf());
+        assert!(!detached.requires_grad());
+
+        (&detached * &x).backward();
+        assert_eq!(x.grad(), 4.0);
+        assert_eq!(y.grad(), 0.0);
+    }
+
+    #[test]
+    fn frozen_leaf_keeps_zero_gradient() {
+        let (w, b, x) = (Scalar::new(3.0), Scalar::new(1.0), Scalar::new(2.0));
+        x.set_requires_grad(false);
+        let x2 = &x * &x;
+        assert!(!x2.requires_grad());
+
+        (&w * &x2 + &b * &b).backward();
+        assert_eq!(w.grad(), 4.0);
+        assert_eq!(b.grad(), 2.0);
+        assert_eq!(x.grad(), 0.0);
+        assert_eq!(x2.grad(), 0.0);
+    }
+
+    fn check_backward<T: Float>(eps: f64, tol: f64) {
+        let (eps, two_eps) = (T::from_f64(eps), T::from_f64(2.0 * eps));
+
+        for op in all_ops::<T>() {
+            for index in 0..20 {
+                let inputs: Vec<T> = sample_inputs(&op, index);
+                let mut grads = vec![T::zero(); inputs.len()];
+                op.backward(&inputs, op.forward(&inputs), T::one(), &mut grads);
+
+                for (i, &grad) in grads.iter().enumerate() {
+                    let (mut above, mut below) = (inputs.clone(), inputs.clone());
+                    above[i] += eps;
+                    below[i] -= eps;
+                    let numeric = (op.forward(&above) - op.forward(&below)) / two_eps;
+                    let what = format!("d{}/dx{} at {:?}", op, i, inputs);
+                    assert_close(grad, numeric, tol, &what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backward_matches_finite_differences_f64() {
+        check_backward::<f64>(1e-6, 1e-6);
+    }
+
+    #[test]
+    fn backward_matches_finite_differences_f32() {
+        check_backward::<f32>(1e-3, 3e-3);
+    }
+
+    #[test]
+    fn pow_gradients_match_finite_differences() {
+        for inputs in [[1.3, -0.7], [0.4, 2.2], [2.5, 0.0]] {
+            let report = gradcheck(|x| x[0].pow(&x[1]), &inputs, 1e-6, 1e-6);
+            assert!(report.passed(), "pow at {:?}:\n{}", inputs, report);
+        }
+    }
+
+    #[test]
+    fn powi_zero_has_zero_gradient_at_zero() {
+        let x = Scalar::new(0.0);
+        x.powi(0).backward();
+        assert_eq!(x.grad(), 0.0);
+
+        let (val, tangent) = jvp(|x: &[Dual]| x[0].powi(0), &[0.0], &[1.0]);
+        assert_eq!((val, tangent), (1.0, 0.0));
+    }
+
+    #[test]
+    fn custom_op_takes_more_than_two_inputs() {
+        let product = CustomOp::new(Product3);
+        let (x, y, z) = (Scalar::new(2.0), Scalar::new(3.0), Scalar::new(-0.5));
+        let out = Scalar::apply(&product, &[&x, &y, &z]);
+        assert_eq!(out.val(), -3.0);
+
+        out.backward();
+        assert_eq!((x.grad(), y.grad(), z.grad()), (-1.5, -1.0, 6.0));
+
+        let report = gradcheck(
+            |x| Scalar::apply(&product, &[&x[0], &x[1], &x[2]]),
+            &[0.7, -1.2, 1.9],
+            1e-6,
+            1e-6,
+        );
+        assert!(report.passed(), "{}", report);
+    }
+
+    #[test]
+    fn sum_does_not_add_a_trainable_leaf() {
+        let constants = [Scalar::constant(1.0), Scalar::constant(2.0)];
+        let sum: Scalar<f64> = constants.iter().sum();
+        assert_eq!(sum.val(), 3.0);
+        assert!(!sum.requires_grad());
+
+        let empty: Scalar<f64> = std::iter::empty::<Scalar<f64>>().sum();
+        assert_eq!(empty.val(), 0.0);
+        assert!(!empty.requires_grad());
 
-        Cow::from(childs)
+        let xs = [Scalar::new(1.0), Scalar::new(-2.0), Scalar::new(4.0)];
+        let sum: Scalar<f64> = xs.iter().map(|x| x * 3.0).sum();
+        assert_eq!(sum.val(), 9.0);
+        let leaves = sum.topological_order(|_| true);
+        let trainable = leaves.iter().filter(|x| x.is_leaf() && x.requires_grad());
+        assert_eq!(trainable.count(), xs.len());
+        sum.backward();
+        assert!(xs.iter().all(|x| x.grad() == 3.0));
     }
 }
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Named lists of numbers describing the state of an optimizer or scheduler,
+/// e.g. the step count and the moving averages of every parameter.
+///
+/// Values are stored as `f64`, which holds every `f32` and all step counts
+/// exactly. The [`Display`](fmt::Display) output is a line based text format
+/// which can be read back with [`str::parse`]:
+///
+/// ```text
+/// lr 0.01
+/// step 12
+/// exp_avg 0.5 -0.25 0.125
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StateDict {
+    entries: BTreeMap<String, Vec<f64>>,
+}
+
+impl StateDict {
+    pub fn new() -> StateDict {
+        StateDict::default()
+    }
+
+    /// Stores `values` under `key`, replacing previous values.
+    ///
+    /// # Panics
+    ///
+    /// If `key` is empty or contains whitespace, it could not be parsed again.
+    pub fn insert(&mut self, key: impl Into<String>, values: Vec<f64>) {
+        let key = key.into();
+        assert!(
+            !key.is_empty() && !key.contains(char::is_whitespace),
+            "invalid state key {:?}",
+            key
+        );
+        self.entries.insert(key, values);
+    }
+
+    pub fn insert_value(&mut self, key: impl Into<String>, value: f64) {
+        self.insert(key, vec![value]);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&[f64]> {
+        self.entries.get(key).map(Vec::as_slice)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Values under `key`, checking that there are `len` of them.
+    pub fn values(&self, key: &str, len: usize) -> Result<&[f64], StateError> {
+        let values = self
+            .get(key)
+            .ok_or_else(|| StateError::Missing(key.to_string()))?;
+        if values.len() != len {
+            return Err(StateError::Length {
+                key: key.to_string(),
+                expected: len,
+                found: values.len(),
+            });
+        }
+        Ok(values)
+    }
+
+    /// The single value under `key`.
+    pub fn value(&self, key: &str) -> Result<f64, StateError> {
+        self.values(key, 1).map(|values| values[0])
+    }
+
+    /// Adds all entries of `other` with keys prefixed by `prefix.`, to store
+    /// e.g. an optimizer and its scheduler in one checkpoint.
+    pub fn merge(&mut self, prefix: &str, other: &StateDict) {
+        for (key, values) in &other.entries {
+            self.insert(format!("{}.{}", prefix, key), values.clone());
+        }
+    }
+
+    /// Entries with keys starting with `prefix.`, with the prefix removed.
+    pub fn sub_dict(&self, prefix: &str) -> StateDict {
+        let prefix = format!("{}.", prefix);
+        let entries = self
+            .entries
+            .iter()
+            .filter_map(|(key, values)| {
+                key.strip_prefix(&prefix)
+                    .map(|key| (key.to_string(), values.clone()))
+            })
+            .collect();
+        StateDict { entries }
+    }
+}
+
+impl fmt::Display for StateDict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, values) in &self.entries {
+            write!(f, "{}", key)?;
+            for value in values {
+                write!(f, " {}", value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for StateDict {
+    type Err = StateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut state = StateDict::new();
+        for (index, line) in s.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let key = match words.next() {
+                Some(key) => key,
+                None => continue,
+            };
+            let values = words
+                .map(f64::from_str)
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| StateError::Parse { line: index + 1 })?;
+            state.insert(key, values);
+        }
+        Ok(state)
+    }
+}
+
+/// Reasons a [`StateDict`] cannot be parsed or loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// A line contains something else than numbers after its key.
+    Parse { line: usize },
+    /// A required key is not present.
+    Missing(String),
+    /// A key holds a different number of values than expected, e.g. the
+    /// state was saved for another model.
+    Length {
+        key: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A key holds a value out of range, e.g. a phase the schedule does not
+    /// have.
+    Invalid(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Parse { line } => write!(f, "invalid number in line {}", line),
+            StateError::Missing(key) => write!(f, "missing state {:?}", key),
+            StateError::Length {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "state {:?} has {} values, expected {}",
+                key, found, expected
+            ),
+            StateError::Invalid(key) => write!(f, "invalid value of state {:?}", key),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_parse_round_trip() {
+        let mut state = StateDict::new();
+        state.insert_value("lr", 0.01);
+        state.insert_value("step", 12.0);
+        state.insert("exp_avg", vec![0.5, -0.25, 1e-300, f64::from(0.1f32)]);
+        state.insert("empty", Vec::new());
+
+        let text = state.to_string();
+        assert_eq!(text.lines().next(), Some("empty"));
+        assert_eq!(text.parse::<StateDict>(), Ok(state));
+    }
+
+    #[test]
+    fn merge_and_sub_dict() {
+        let mut inner = StateDict::new();
+        inner.insert_value("lr", 0.1);
+
+        let mut state = StateDict::new();
+        state.merge("optimizer", &inner);
+        assert_eq!(state.keys().collect::<Vec<_>>(), ["optimizer.lr"]);
+        assert_eq!(state.sub_dict("optimizer"), inner);
+        assert_eq!(state.sub_dict("scheduler"), StateDict::new());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "lr 0.1\nstep x\n".parse::<StateDict>(),
+            Err(StateError::Parse { line: 2 })
+        );
+
+        let state: StateDict = "sum 1 2\n".parse().unwrap();
+        assert_eq!(
+            state.value("lr"),
+            Err(StateError::Missing("lr".to_string()))
+        );
+        assert_eq!(
+            state.values("sum", 3),
+            Err(StateError::Length {
+                key: "sum".to_string(),
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+}
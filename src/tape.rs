@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops;
+
+use crate::float::Float;
+use crate::grad_mode;
+use crate::scalar::ScalarOp;
+
+const NO_PARENT: usize = usize::MAX;
+
+#[derive(Debug)]
+struct Nodes<T: Float> {
+    ops: Vec<ScalarOp<T>>,
+    parents: Vec<[usize; 2]>,
+    vals: Vec<T>,
+    grads: Vec<T>,
+}
+
+/// Arena holding a whole computation graph in contiguous vectors.
+///
+/// Every operation on a [`Var`] appends one node to the tape, so the tape is
+/// always in topological order and backward is a single reverse sweep. Vars
+/// borrow the tape, so they have to be dropped before the tape can be
+/// [`cleared`](Tape::clear) and reused for the next training step.
+#[derive(Debug)]
+pub struct Tape<T: Float = f32> {
+    nodes: RefCell<Nodes<T>>,
+}
+
+/// Lightweight handle to a node on a [`Tape`].
+#[derive(Clone, Copy)]
+pub struct Var<'t, T: Float = f32> {
+    tape: &'t Tape<T>,
+    index: usize,
+}
+
+impl<T: Float> Tape<T> {
+    pub fn new() -> Tape<T> {
+        Tape::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Tape<T> {
+        Tape {
+            nodes: RefCell::new(Nodes {
+                ops: Vec::with_capacity(capacity),
+                parents: Vec::with_capacity(capacity),
+                vals: Vec::with_capacity(capacity),
+                grads: Vec::with_capacity(capacity),
+            }),
+        }
+    }
+
+    pub fn var(&self, val: T) -> Var<'_, T> {
+        self.push(val, [NO_PARENT, NO_PARENT], ScalarOp::None)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().vals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all nodes but keeps the allocated memory.
+    pub fn clear(&mut self) {
+        let nodes = self.nodes.get_mut();
+        nodes.ops.clear();
+        nodes.parents.clear();
+        nodes.vals.clear();
+        nodes.grads.clear();
+    }
+
+    fn push(&self, val: T, parents: [usize; 2], op: ScalarOp<T>) -> Var<'_, T> {
+        let (parents, op) = if grad_mode::is_grad_enabled() {
+            (parents, op)
+        } else {
+            ([NO_PARENT, NO_PARENT], ScalarOp::None)
+        };
+
+        let mut nodes = self.nodes.borrow_mut();
+        let index = nodes.vals.len();
+
+        nodes.ops.push(op);
+        nodes.parents.push(parents);
+        nodes.vals.push(val);
+        nodes.grads.push(T::zero());
+
+        Var { tape: self, index }
+    }
+
+    fn backward(&self, root: usize) {
+        let mut guard = self.nodes.borrow_mut();
+        let nodes = &mut *guard;
+
+        nodes.grads.fill(T::zero());
+        nodes.grads[root] = T::one();
+
+        for index in (0..=root).rev() {
+            let op = &nodes.ops[index];
+            if *op == ScalarOp::None {
+                continue;
+            }
+
+            let parents = nodes.parents[index];
+            let arity = if parents[1] == NO_PARENT { 1 } else { 2 };
+            let inputs = parents.map(|parent| nodes.vals.get(parent).copied().unwrap_or(T::zero()));
+            let mut grads = [T::zero(); 2];
+            op.backward(
+                &inputs[..arity],
+                nodes.vals[index],
+                nodes.grads[index],
+                &mut grads[..arity],
+            );
+
+            for (&parent, grad) in parents[..arity].iter().zip(grads) {
+                nodes.grads[parent] += grad;
+            }
+        }
+    }
+}
+
+impl<T: Float> Default for Tape<T> {
+    fn default() -> Self {
+        Tape::new()
+    }
+}
+
+impl<'t, T: Float> Var<'t, T> {
+    pub fn val(&self) -> T {
+        self.tape.nodes.borrow().vals[self.index]
+    }
+
+    pub fn grad(&self) -> T {
+        self.tape.nodes.borrow().grads[self.index]
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// New leaf on the same tape with the value of `self`.
+    pub fn detach(self) -> Var<'t, T> {
+        self.tape.var(self.val())
+    }
+
+    /// Computes the gradients of all nodes recorded before `self`, gradients
+    /// of a previous backward pass are overwritten.
+    pub fn backward(&self) {
+        self.tape.backward(self.index);
+    }
+
+    pub fn tanh(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Tanh)
+    }
+
+    pub fn powi(self, n: i32) -> Var<'t, T> {
+        self.unary(ScalarOp::Powi(n))
+    }
+
+    pub fn powf(self, n: T) -> Var<'t, T> {
+        self.unary(ScalarOp::Powf(n))
+    }
+
+    pub fn pow(self, exponent: Var<'t, T>) -> Var<'t, T> {
+        self.binary(exponent, ScalarOp::Pow)
+    }
+
+    pub fn exp(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Exp)
+    }
+
+    pub fn ln(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Ln)
+    }
+
+    pub fn log2(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Log2)
+    }
+
+    pub fn log10(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Log10)
+    }
+
+    pub fn sqrt(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Sqrt)
+    }
+
+    pub fn recip(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Recip)
+    }
+
+    pub fn sin(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Sin)
+    }
+
+    pub fn cos(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Cos)
+    }
+
+    pub fn tan(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Tan)
+    }
+
+    pub fn abs(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Abs)
+    }
+
+    pub fn relu(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Relu)
+    }
+
+    pub fn leaky_relu(self, alpha: T) -> Var<'t, T> {
+        self.unary(ScalarOp::LeakyRelu(alpha))
+    }
+
+    pub fn sigmoid(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Sigmoid)
+    }
+
+    pub fn gelu(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Gelu)
+    }
+
+    pub fn silu(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Silu)
+    }
+
+    pub fn softplus(self) -> Var<'t, T> {
+        self.unary(ScalarOp::Softplus)
+    }
+
+    pub fn elu(self, alpha: T) -> Var<'t, T> {
+        self.unary(ScalarOp::Elu(alpha))
+    }
+
+    fn unary(self, op: ScalarOp<T>) -> Var<'t, T> {
+        let val = op.forward(&[self.val()]);
+        self.tape.push(val, [self.index, NO_PARENT], op)
+    }
+
+    fn binary(self, rhs: Var<'t, T>, op: ScalarOp<T>) -> Var<'t, T> {
+        assert!(
+            std::ptr::eq(self.tape, rhs.tape),
+            "vars belong to different tapes"
+        );
+        let val = op.forward(&[self.val(), rhs.val()]);
+        self.tape.push(val, [self.index, rhs.index], op)
+    }
+}
+
+macro_rules! impl_var_binop {
+    ($imp:ident, $method:ident, $op:expr) => {
+        impl<'t, T: Float> ops::$imp for Var<'t, T> {
+            type Output = Var<'t, T>;
+
+            fn $method(self, rhs: Var<'t, T>) -> Self::Output {
+                self.binary(rhs, $op)
+            }
+        }
+
+        impl<'t, T: Float> ops::$imp<T> for Var<'t, T> {
+            type Output = Var<'t, T>;
+
+            fn $method(self, rhs: T) -> Self::Output {
+                self.binary(self.tape.var(rhs), $op)
+            }
+        }
+
+        impl<'t> ops::$imp<Var<'t, f32>> for f32 {
+            type Output = Var<'t, f32>;
+
+            fn $method(self, rhs: Var<'t, f32>) -> Self::Output {
+                rhs.tape.var(self).binary(rhs, $op)
+            }
+        }
+
+        impl<'t> ops::$imp<Var<'t, f64>> for f64 {
+            type Output = Var<'t, f64>;
+
+            fn $method(self, rhs: Var<'t, f64>) -> Self::Output {
+                rhs.tape.var(self).binary(rhs, $op)
+            }
+        }
+    };
+}
+
+impl_var_binop!(Add, add, ScalarOp::Add);
+impl_var_binop!(Sub, sub, ScalarOp::Sub);
+impl_var_binop!(Mul, mul, ScalarOp::Mul);
+impl_var_binop!(Div, div, ScalarOp::Div);
+
+impl<'t, T: Float> ops::Neg for Var<'t, T> {
+    type Output = Var<'t, T>;
+
+    fn neg(self) -> Self::Output {
+        self.unary(ScalarOp::Neg)
+    }
+}
+
+impl<T: Float> fmt::Debug for Var<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Var")
+            .field("index", &self.index)
+            .field("val", &self.val())
+            .field("grad", &self.grad())
+            .finish()
+    }
+}
+
+impl<T: Float> fmt::Display for Var<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = self.tape.nodes.borrow().ops[self.index].clone();
+        if op != ScalarOp::None {
+            write!(f, "Val({}; Δ{})<- {}", self.val(), self.grad(), op)
+        } else {
+            write!(f, "Val({}; Δ{})", self.val(), self.grad())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, numeric_derivative};
+
+    type UnaryFn = for<'t> fn(Var<'t, f64>) -> Var<'t, f64>;
+
+    #[test]
+    fn shared_node_accumulates_once_per_path() {
+        let tape = Tape::new();
+        let a = tape.var(3.0);
+        let b = a * a;
+        let c = b + b;
+        c.backward();
+        assert_eq!(b.grad(), 2.0);
+        assert_eq!(a.grad(), 4.0 * 3.0);
+    }
+
+    #[test]
+    fn backward_overwrites_previous_gradients() {
+        let tape = Tape::new();
+        let x = tape.var(2.0);
+        let y = x * 3.0;
+        let z = x * x;
+
+        y.backward();
+        assert_eq!(x.grad(), 3.0);
+        z.backward();
+        assert_eq!(x.grad(), 4.0);
+        assert_eq!(y.grad(), 0.0);
+    }
+
+    #[test]
+    fn clear_keeps_capacity() {
+        let mut tape = Tape::with_capacity(8);
+        for _ in 0..3 {
+            let x = tape.var(1.5);
+            let y = (x * x).tanh() + x;
+            y.backward();
+            assert_eq!(tape.len(), 4);
+
+            tape.clear();
+            assert!(tape.is_empty());
+            assert!(tape.nodes.get_mut().vals.capacity() >= 8);
+        }
+    }
+
+    #[test]
+    fn elementary_functions_match_finite_differences() {
+        let functions: [(&str, UnaryFn, &[f64]); 12] = [
+            ("exp", |x| x.exp(), &[-1.3, 0.4, 1.7]),
+            ("ln", |x| x.ln(), &[0.3, 1.0, 2.5]),
+            ("log2", |x| x.log2(), &[0.3, 1.0, 2.5]),
+            ("log10", |x| x.log10(), &[0.3, 1.0, 2.5]),
+            ("sqrt", |x| x.sqrt(), &[0.3, 1.0, 2.5]),
+            ("powf", |x| x.powf(2.5), &[0.3, 1.0, 2.5]),
+            ("sin", |x| x.sin(), &[-2.0, 0.0, 1.1]),
+            ("cos", |x| x.cos(), &[-2.0, 0.0, 1.1]),
+            ("tan", |x| x.tan(), &[-1.0, 0.0, 1.2]),
+            ("abs", |x| x.abs(), &[-1.5, 0.5, 2.0]),
+            ("recip", |x| x.recip(), &[-1.5, 0.5, 2.0]),
+            ("powi", |x| x.powi(-3), &[-1.5, 0.5, 2.0]),
+        ];
+
+        for (name, f, points) in functions {
+            for &x in points {
+                let tape = Tape::new();
+                let var = tape.var(x);
+                f(var).backward();
+
+                let numeric = numeric_derivative(|x| f(Tape::new().var(x)).val(), x, 1e-6);
+                assert_close(var.grad(), numeric, 1e-6, format!("{} at {}", name, x));
+            }
+        }
+    }
+
+    #[test]
+    fn pow_gradients_match_finite_differences() {
+        for (base, exponent) in [(1.3, -0.7), (0.4, 2.2), (2.5, 0.0)] {
+            let tape = Tape::new();
+            let (x, y) = (tape.var(base), tape.var(exponent));
+            x.pow(y).backward();
+
+            let pow = |base: f64, exponent: f64| {
+                let tape = Tape::new();
+                tape.var(base).pow(tape.var(exponent)).val()
+            };
+            let what = format!("pow({}, {})", base, exponent);
+            let numeric = numeric_derivative(|x| pow(x, exponent), base, 1e-6);
+            assert_close(x.grad(), numeric, 1e-6, &what);
+            let numeric = numeric_derivative(|y| pow(base, y), exponent, 1e-6);
+            assert_close(y.grad(), numeric, 1e-6, &what);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Helpers shared by the unit tests.
+
+use std::fmt;
+use std::iter;
+
+use crate::float::Float;
+use crate::op::{CustomOp, Op};
+use crate::scalar::{Scalar, ScalarOp};
+
+/// Central difference `(f(x + eps) - f(x - eps)) / 2 eps`.
+pub(crate) fn numeric_derivative<T: Float>(f: impl Fn(T) -> T, x: T, eps: T) -> T {
+    (f(x + eps) - f(x - eps)) / (eps + eps)
+}
+
+/// Asserts that `actual` is within `tol` of `expected`, relative to
+/// `1 + |expected|`.
+#[track_caller]
+pub(crate) fn assert_close<T: Float>(actual: T, expected: T, tol: f64, what: impl fmt::Display) {
+    let (actual, expected) = (actual.to_f64(), expected.to_f64());
+    assert!(
+        (actual - expected).abs() <= tol * (1.0 + expected.abs()),
+        "{}: {} != {}",
+        what,
+        actual,
+        expected
+    );
+}
+
+#[derive(Debug)]
+pub(crate) struct Cube;
+
+impl<T: Float> Op<T> for Cube {
+    fn name(&self) -> String {
+        "cube".to_string()
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn forward(&self, inputs: &[T]) -> T {
+        inputs[0].powi(3)
+    }
+
+    fn backward(&self, inputs: &[T], _out: T, grad: T) -> Vec<T> {
+        vec![T::from_f64(3.0) * inputs[0].powi(2) * grad]
+    }
+
+    fn backward_graph(
+        &self,
+        inputs: &[Scalar<T>],
+        _out: &Scalar<T>,
+        grad: &Scalar<T>,
+    ) -> Vec<Scalar<T>> {
+        vec![grad * inputs[0].powi(2) * T::from_f64(3.0)]
+    }
+}
+
+// every operation except `None`, leaves have nothing to differentiate
+pub(crate) fn all_ops<T: Float>() -> Vec<ScalarOp<T>> {
+    let c = T::from_f64(0.7);
+    vec![
+        ScalarOp::Abs,
+        ScalarOp::Add,
+        ScalarOp::AddConst(c),
+        ScalarOp::Cos,
+        ScalarOp::Div,
+        ScalarOp::Elu(c),
+        ScalarOp::Exp,
+        ScalarOp::Gelu,
+        ScalarOp::LeakyRelu(c),
+        ScalarOp::Ln,
+        ScalarOp::Log10,
+        ScalarOp::Log2,
+        ScalarOp::Mul,
+        ScalarOp::MulConst(c),
+        ScalarOp::Neg,
+        ScalarOp::Pow,
+        ScalarOp::Powf(c),
+        ScalarOp::Powi(-2),
+        ScalarOp::Powi(0),
+        ScalarOp::Powi(3),
+        ScalarOp::Recip,
+        ScalarOp::Relu,
+        ScalarOp::Sigmoid,
+        ScalarOp::Silu,
+        ScalarOp::Sin,
+        ScalarOp::Softplus,
+        ScalarOp::Sqrt,
+        ScalarOp::Sub,
+        ScalarOp::Tan,
+        ScalarOp::Tanh,
+        ScalarOp::Custom(CustomOp::new(Cube)),
+    ]
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Domain {
+    Any,
+    NonZero,
+    Positive,
+    Small,
+}
+
+impl Domain {
+    /// Maps `unit` in `[0, 1)` into the domain.
+    fn at<T: Float>(self, unit: f64) -> T {
+        let val = match self {
+            Domain::Any => -2.0 + 4.0 * unit,
+            Domain::NonZero => {
+                let magnitude = 0.1 + 1.9 * (2.0 * unit).fract();
+                if unit < 0.5 {
+                    -magnitude
+                } else {
+                    magnitude
+                }
+            }
+            Domain::Positive => 0.5 + 1.5 * unit,
+            Domain::Small => -1.0 + 2.0 * unit,
+        };
+        T::from_f64(val)
+    }
+}
+
+/// Domains of the operands of `op` that avoid its kinks and poles, the
+/// second one is `None` for unary operations.
+pub(crate) fn domains<T: Float>(op: &ScalarOp<T>) -> (Domain, Option<Domain>) {
+    match op {
+        ScalarOp::Add | ScalarOp::Sub | ScalarOp::Mul => (Domain::Any, Some(Domain::Any)),
+        ScalarOp::Div => (Domain::Any, Some(Domain::NonZero)),
+        ScalarOp::Pow => (Domain::Positive, Some(Domain::Any)),
+        ScalarOp::Ln | ScalarOp::Log10 | ScalarOp::Log2 | ScalarOp::Sqrt | ScalarOp::Powf(_) => {
+            (Domain::Positive, None)
+        }
+        ScalarOp::Abs
+        | ScalarOp::Relu
+        | ScalarOp::LeakyRelu(_)
+        | ScalarOp::Elu(_)
+        | ScalarOp::Recip
+        | ScalarOp::Powi(_) => (Domain::NonZero, None),
+        ScalarOp::Tan => (Domain::Small, None),
+        ScalarOp::None
+        | ScalarOp::AddConst(_)
+        | ScalarOp::MulConst(_)
+        | ScalarOp::Neg
+        | ScalarOp::Cos
+        | ScalarOp::Sin
+        | ScalarOp::Exp
+        | ScalarOp::Tanh
+        | ScalarOp::Gelu
+        | ScalarOp::Sigmoid
+        | ScalarOp::Silu
+        | ScalarOp::Softplus
+        | ScalarOp::Custom(_) => (Domain::Any, None),
+    }
+}
+
+/// Operands of `op` inside its domains, spread evenly over them as `index`
+/// increases (additive recurrence with irrational steps).
+pub(crate) fn sample_inputs<T: Float>(op: &ScalarOp<T>, index: usize) -> Vec<T> {
+    let (lhs, rhs) = domains(op);
+    let unit = |step: f64| (0.5 + index as f64 * step).fract();
+    iter::once(lhs.at(unit(0.618_033_988_749_895)))
+        .chain(rhs.map(|domain| domain.at(unit(0.414_213_562_373_095))))
+        .collect()
+}
@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::io;
+use std::rc::Rc;
+
+use ptree::style::{Color, Style};
+use ptree::TreeItem;
+
+use crate::float::Float;
+use crate::scalar::{Scalar, ScalarOp};
+
+/// Options for printing a graph with [`Scalar::tree`].
+#[derive(Debug, Clone, Default)]
+pub struct TreeOptions {
+    /// Number of decimals for values and gradients, full precision if `None`.
+    pub precision: Option<usize>,
+    /// Nodes deeper than this are not expanded, `Some(0)` prints only the root.
+    pub max_depth: Option<usize>,
+    /// Colors nodes by the magnitude of their gradient relative to the
+    /// largest gradient in the graph.
+    pub colorize: bool,
+    /// Prints the parents of a node reachable over several paths only the
+    /// first time and marks later occurrences.
+    pub mark_shared: bool,
+}
+
+impl TreeOptions {
+    pub fn precision(mut self, precision: usize) -> TreeOptions {
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> TreeOptions {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn colorize(mut self, colorize: bool) -> TreeOptions {
+        self.colorize = colorize;
+        self
+    }
+
+    pub fn mark_shared(mut self, mark_shared: bool) -> TreeOptions {
+        self.mark_shared = mark_shared;
+        self
+    }
+}
+
+/// State shared by all nodes of one printed tree.
+#[derive(Debug)]
+struct Printer<T: Float> {
+    options: TreeOptions,
+    max_grad: T,
+    printed: RefCell<HashSet<usize>>,
+}
+
+/// A [`Scalar`] rendered according to [`TreeOptions`], print it with
+/// `ptree::print_tree`.
+#[derive(Debug, Clone)]
+pub struct ScalarTree<T: Float = f32> {
+    node: Scalar<T>,
+    depth: usize,
+    printer: Rc<Printer<T>>,
+    repeated: Cell<bool>,
+}
+
+impl<T: Float> Scalar<T> {
+    /// View of the graph that computed `self` for `ptree` with the given
+    /// options.
+    ///
+    /// The tree is meant to be printed once, marking shared nodes depends on
+    /// the order ptree visits the nodes.
+    pub fn tree(&self, options: TreeOptions) -> ScalarTree<T> {
+        let max_grad = self
+            .topological_order(|_| true)
+            .iter()
+            .fold(T::zero(), |max, node| max.max(node.grad().abs()));
+
+        ScalarTree {
+            node: self.clone(),
+            depth: 0,
+            printer: Rc::new(Printer {
+                options,
+                max_grad,
+                printed: RefCell::new(HashSet::new()),
+            }),
+            repeated: Cell::new(false),
+        }
+    }
+
+    /// Prints the graph that computed `self` to standard output.
+    pub fn print_tree(&self, options: TreeOptions) -> io::Result<()> {
+        ptree::print_tree(&self.tree(options))
+    }
+}
+
+impl<T: Float> ScalarTree<T> {
+    fn truncated(&self) -> bool {
+        self.printer
+            .options
+            .max_depth
+            .is_some_and(|max_depth| self.depth >= max_depth)
+    }
+
+    fn format_number(&self, val: T) -> String {
+        match self.printer.options.precision {
+            Some(precision) => format!("{:.*}", precision, val),
+            None => format!("{}", val),
+        }
+    }
+
+    fn style(&self, style: &Style) -> Style {
+        let mut style = style.clone();
+        if !self.printer.options.colorize {
+            return style;
+        }
+
+        let grad = self.node.grad().abs();
+        if grad == T::zero() {
+            style.dimmed = true;
+            return style;
+        }
+        let ratio = grad / self.printer.max_grad;
+        style.foreground = Some(if ratio > T::from_f64(0.5) {
+            Color::Red
+        } else if ratio > T::from_f64(0.1) {
+            Color::Yellow
+        } else {
+            Color::Green
+        });
+        style
+    }
+}
+
+impl<T: Float> TreeItem for ScalarTree<T> {
+    type Child = Self;
+
+    fn write_self<W: io::Write>(&self, f: &mut W, style: &Style) -> io::Result<()> {
+        let node = &self.node;
+        let mut text = String::new();
+        if let Some(label) = node.label() {
+            text.push_str(&label);
+            text.push_str(": ");
+        }
+        text.push_str(&format!(
+            "Val({}; Δ{})",
+            self.format_number(node.val()),
+            self.format_number(node.grad())
+        ));
+        if *node.op() != ScalarOp::None {
+            text.push_str(&format!("<- {}", node.op()));
+        }
+
+        if self.printer.options.mark_shared && !node.is_leaf() {
+            let first = self.printer.printed.borrow_mut().insert(node.id());
+            self.repeated.set(!first);
+            if !first {
+                text.push_str(" (repeated)");
+            }
+        }
+        if self.truncated() && !node.is_leaf() {
+            text.push_str(" …");
+        }
+
+        write!(f, "{}", self.style(style).paint(text))
+    }
+
+    fn children(&self) -> Cow<'_, [Self::Child]> {
+        if self.repeated.get() || self.truncated() {
+            return Cow::from(Vec::new());
+        }
+
+        let children = self
+            .node
+            .parents()
+            .iter()
+            .map(|parent| ScalarTree {
+                node: parent.clone(),
+                depth: self.depth + 1,
+                printer: Rc::clone(&self.printer),
+                repeated: Cell::new(false),
+            })
+            .collect::<Vec<_>>();
+        Cow::from(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(node: &Scalar<f64>, options: TreeOptions) -> String {
+        let mut buf = Vec::new();
+        ptree::write_tree(&node.tree(options), &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn diamond() -> Scalar<f64> {
+        let a = Scalar::new(2.0).with_label("a");
+        let b = (&a * &a).with_label("b");
+        let c = (&b + &b).with_label("c");
+        c.backward();
+        c
+    }
+
+    #[test]
+    fn full_tree_with_labels() {
+        let expected = "\
+c: Val(8; Δ1)<- +
+├─ b: Val(4; Δ2)<- *
+│  ├─ a: Val(2; Δ8)
+│  └─ a: Val(2; Δ8)
+└─ b: Val(4; Δ2)<- *
+   ├─ a: Val(2; Δ8)
+   └─ a: Val(2; Δ8)
+";
+        assert_eq!(render(&diamond(), TreeOptions::default()), expected);
+    }
+
+    #[test]
+    fn precision_and_shared_nodes() {
+        let options = TreeOptions::default().precision(1).mark_shared(true);
+        let expected = "\
+c: Val(8.0; Δ1.0)<- +
+├─ b: Val(4.0; Δ2.0)<- *
+│  ├─ a: Val(2.0; Δ8.0)
+│  └─ a: Val(2.0; Δ8.0)
+└─ b: Val(4.0; Δ2.0)<- * (repeated)
+";
+        assert_eq!(render(&diamond(), options), expected);
+    }
+
+    #[test]
+    fn max_depth() {
+        let expected = "c: Val(8; Δ1)<- + …\n";
+        assert_eq!(
+            render(&diamond(), TreeOptions::default().max_depth(0)),
+            expected
+        );
+
+        let output = render(&diamond(), TreeOptions::default().max_depth(1));
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.lines().skip(1).all(|line| line.ends_with("<- * …")));
+    }
+}